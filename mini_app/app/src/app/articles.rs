@@ -1,13 +1,14 @@
-use std::fs::read_dir;
+use std::{fs::read_dir, io};
 
-use common::{dto::ArticalListReqBody, result::HttpResult};
+use common::dto::ArticalListReqBody;
+use mini_runtime::spawn_blocking;
 
 use crate::{request::ServerRequest, response::ServerResponse, route::THttpMethodHandler};
 
 pub struct ArticleListHandler {}
 
 impl ArticleListHandler {
-    fn load_artiles() -> HttpResult<Vec<String>> {
+    fn load_artiles() -> io::Result<Vec<String>> {
         let dirs = read_dir("./static/text")?;
         Ok(dirs
             .filter_map(|dir| {
@@ -35,7 +36,7 @@ impl THttpMethodHandler for ArticleListHandler {
         response: ServerResponse,
     ) -> Option<crate::HttpBoxedFuture<'_, ()>> {
         Some(Box::pin(async move {
-            let articles = Self::load_artiles()?;
+            let articles = spawn_blocking(Self::load_artiles).await??;
             response
                 .lock()
                 .await
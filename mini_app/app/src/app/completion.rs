@@ -9,7 +9,7 @@ use mini_runtime::{sleep, web::conn::TcpConn};
 
 use crate::{
     app::response::TextRandomSplitter,
-    helper::load_file,
+    helper::load_file_async,
     request::ServerRequest,
     response::{SSEResponse, ServerResponse},
     route::THttpMethodHandler,
@@ -27,7 +27,7 @@ impl CompletionHandler {
             .unwrap_or(DEFAULT_ARTICLE.to_owned());
         log::info!("asked article: {}", article);
 
-        let content = load_file(format!("./static/text/{}.txt", article).as_str());
+        let content = load_file_async(format!("./static/text/{}.txt", article)).await;
         let content = match content {
             Ok(content) => content,
             Err(e) => {
@@ -10,9 +10,9 @@ use crate::{
     Redirect,
     app::{
         articles::ArticleListHandler, completion::CompletionHandler, home::HomeHandler,
-        html::HtmlGetterHandler,
+        html::HtmlGetterHandler, notice::NoticeHandler,
     },
-    route::add_route_handler,
+    route::{add_limited_route_handler, add_route_handler},
     route_handler,
 };
 
@@ -20,8 +20,11 @@ mod articles;
 mod completion;
 mod home;
 mod html;
+mod notice;
 mod response;
 
+const MAX_COMPLETION_CONCURRENCY: usize = 8;
+
 fn init_route() {
     add_route_handler("".into(), Redirect::to("/home".to_owned()), None);
     add_route_handler("/".into(), Redirect::to("/home".to_owned()), None);
@@ -42,11 +45,14 @@ fn init_route() {
         None,
     );
     add_route_handler("/article_list".into(), ArticleListHandler {}, None);
-    add_route_handler(
+    // 补全请求耗时较长，限制同时处理的数量
+    add_limited_route_handler(
         "/completion".into(),
         CompletionHandler {},
         Some(time::Duration::from_secs(20).into()),
+        MAX_COMPLETION_CONCURRENCY,
     );
+    add_route_handler("/notice".into(), NoticeHandler::default(), None);
 }
 
 pub fn create_app(
@@ -0,0 +1,85 @@
+use common::{
+    CT_EVENT_STREAM,
+    dto::{NoticeReqBody, NoticeRespBody, SSEContent},
+    result::HttpResult,
+    sse_proto::SSEProto,
+};
+use mini_runtime::{sync::broadcast, web::conn::TcpConn};
+
+use crate::{
+    request::ServerRequest,
+    response::{SSEResponse, ServerResponse},
+    route::THttpMethodHandler,
+};
+
+// 每个订阅端最多缓存的通知数量，落后更多时跳过旧的通知
+const NOTICE_CAPACITY: usize = 16;
+
+// GET以SSE的形式持续推送通知，POST将通知广播给当前全部的订阅端
+pub struct NoticeHandler {
+    sender: broadcast::Sender<SSEProto>,
+}
+
+impl Default for NoticeHandler {
+    fn default() -> Self {
+        // 只保留发送端，新的订阅端只会收到订阅之后发布的通知
+        let (sender, _) = broadcast::channel(NOTICE_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl NoticeHandler {
+    async fn subscribe(&self, response: ServerResponse) -> HttpResult<()> {
+        let mut rx = self.sender.subscribe();
+        let mut sse_response: SSEResponse<TcpConn> = response
+            .lock()
+            .await
+            .update_header(|header| {
+                header
+                    .set_content_type(CT_EVENT_STREAM.into())
+                    .set_cache_control("no-cache".into())
+                    .set_connection("keep-alive".into());
+            })
+            .chunk()
+            .await?
+            .into();
+        // 客户端断开时写出失败而结束
+        sse_response.forward(&mut rx).await?;
+        sse_response
+            .write_event(SSEContent::stop().into())
+            .close()
+            .await
+    }
+
+    async fn publish(&self, request: ServerRequest, response: ServerResponse) -> HttpResult<()> {
+        let notice: NoticeReqBody = request.json().await?;
+        // 没有订阅端时通知直接丢弃
+        let receivers = self
+            .sender
+            .send(SSEContent::resume(notice.content).into())
+            .unwrap_or(0);
+        response
+            .lock()
+            .await
+            .json(&NoticeRespBody::new(receivers))
+            .await
+    }
+}
+
+impl THttpMethodHandler for NoticeHandler {
+    fn get(
+        &self,
+        _request: ServerRequest,
+        response: ServerResponse,
+    ) -> Option<crate::HttpBoxedFuture<'_, ()>> {
+        Some(Box::pin(self.subscribe(response)))
+    }
+
+    fn post(
+        &self,
+        request: ServerRequest,
+        response: ServerResponse,
+    ) -> Option<crate::HttpBoxedFuture<'_, ()>> {
+        Some(Box::pin(self.publish(request, response)))
+    }
+}
@@ -37,11 +37,13 @@ mod tests {
 
     use mini_runtime::sleep;
 
-    use crate::{app::response::TextRandomSplitter, helper::load_file};
+    use crate::{app::response::TextRandomSplitter, helper::load_file_async};
 
     #[rt_entry::rt_test]
     async fn test_text_splitter() {
-        let content = load_file("./static/text/one_dream.txt").unwrap();
+        let content = load_file_async("./static/text/one_dream.txt".to_owned())
+            .await
+            .unwrap();
         let splitter = TextRandomSplitter::new(content);
 
         for chunk in splitter {
@@ -1,10 +1,9 @@
-use std::{fs, io::Read};
+use std::fs;
 
 use common::result::HttpResult;
+use mini_runtime::spawn_blocking;
 
-pub fn load_file(path: &str) -> HttpResult<String> {
-    let mut file = fs::File::options().read(true).open(path)?;
-    let mut content = String::new();
-    file.read_to_string(&mut content)?;
-    Ok(content)
+// 在阻塞线程池中读取文件，避免阻塞事件循环
+pub async fn load_file_async(path: String) -> HttpResult<String> {
+    Ok(spawn_blocking(move || fs::read_to_string(path)).await??)
 }
@@ -1,12 +1,18 @@
 #![feature(string_into_chars)]
 
-use std::time;
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time,
+};
 
 use common::{
     HttpProtocol, HttpStatus,
     result::{HttpError, HttpResult},
 };
-use mini_runtime::{BoxedFutureWithError, variable_log, web::conn::SharedTcpConn};
+use mini_runtime::{
+    BoxedFutureWithError, ConnTimeout, REQUEST_ID, timeout_at, variable_log,
+    web::conn::SharedTcpConn,
+};
 
 use crate::{
     request::{_ServerRequest, ServerRequest},
@@ -22,7 +28,20 @@ pub(crate) mod route;
 
 pub type HttpBoxedFuture<'a, T> = BoxedFutureWithError<'a, T, HttpError>;
 
+// 进程内递增的请求id，多线程运行时下各线程共享
+static NEXT_REQUEST_ID: AtomicUsize = AtomicUsize::new(1);
+
+// 为每个请求分配id，请求处理过程中的日志都会带上request_id
 pub async fn route_handler(conn: SharedTcpConn) -> HttpResult<()> {
+    let request_id = format!(
+        "{:x}-{}",
+        std::process::id(),
+        NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+    );
+    REQUEST_ID.scope(request_id, handle_request(conn)).await
+}
+
+async fn handle_request(conn: SharedTcpConn) -> HttpResult<()> {
     let request = _ServerRequest::new(conn.clone().into()).await;
     let (path, response) = request
         .as_ref()
@@ -81,6 +100,24 @@ async fn request_route(
         conn.lock().await.set_timeout(timeout);
     }
 
+    // 获取路由的并发许可，处理结束后释放；许可按FIFO顺序分配，排队超过路由的超时时长时返回503
+    let _permit = match handler.get_limiter() {
+        Some(limiter) => {
+            let deadline = handler
+                .get_timeout()
+                .unwrap_or_else(|| ConnTimeout::new(None))
+                .deadline();
+            match timeout_at(deadline, limiter.acquire()).await {
+                Ok(Ok(permit)) => Some(permit),
+                Ok(Err(e)) | Err(e) => {
+                    log::warn!("route '{}' is unavailable: {:?}", path, e);
+                    return service_unavailable(response).await;
+                }
+            }
+        }
+        None => None,
+    };
+
     if let Some(method_handler) = handler.get_handler(request, response.clone()) {
         method_handler.await
     } else {
@@ -115,6 +152,15 @@ async fn not_found(response: ServerResponse) -> HttpResult<()> {
         .await
 }
 
+async fn service_unavailable(response: ServerResponse) -> HttpResult<()> {
+    response
+        .lock()
+        .await
+        .set_status(HttpStatus::ServiceUnavailable)
+        .html_file("./static/error/service_unavailable.html")
+        .await
+}
+
 async fn internal_server_error(response: ServerResponse) -> HttpResult<()> {
     response
         .lock()
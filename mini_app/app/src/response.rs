@@ -7,12 +7,12 @@ use common::{
 use mini_runtime::{
     err_log,
     io_ext::write::{AsyncBufWriter, TAsyncWrite},
-    sync::mutex::AsyncMutex,
+    sync::{broadcast, mutex::AsyncMutex},
     web::conn::{SharedTcpConn, TcpConn},
 };
 use serde::ser::Serialize;
 
-use crate::helper::load_file;
+use crate::helper::load_file_async;
 
 pub type ServerResponse = Rc<AsyncMutex<_ServerResponse<TcpConn>>>;
 
@@ -57,7 +57,7 @@ impl<W: TAsyncWrite> _ServerResponse<W> {
     }
 
     pub async fn html_file(&mut self, path: &str) -> HttpResult<()> {
-        self.html(&load_file(path)?).await
+        self.html(&load_file_async(path.to_owned()).await?).await
     }
 
     pub async fn html(&mut self, data: &str) -> HttpResult<()> {
@@ -159,6 +159,22 @@ impl<W: TAsyncWrite> SSEResponse<W> {
         self.flush().await?;
         self.response.close().await
     }
+
+    // 将广播通道中的事件逐个写出，直到全部发送端释放；落后时跳过被覆盖的事件
+    pub async fn forward(&mut self, rx: &mut broadcast::Receiver<SSEProto>) -> HttpResult<()> {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    self.write_event(event).flush().await?;
+                }
+                Err(e) if e.is_lagged() => {
+                    log::warn!("sse receiver lagged - {:?}", e);
+                }
+                Err(e) if e.is_closed() => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
 }
 
 impl<W: TAsyncWrite> From<ChunkedResponse<W>> for SSEResponse<W> {
@@ -169,3 +185,64 @@ impl<W: TAsyncWrite> From<ChunkedResponse<W>> for SSEResponse<W> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, time};
+
+    use common::{HttpProtocol, sse_proto::SSEProto};
+    use mini_runtime::{
+        io_ext::write::TAsyncWrite,
+        sleep,
+        sync::{broadcast, mutex::AsyncMutex},
+    };
+
+    use crate::response::{_ServerResponse, SSEResponse};
+
+    // 写入内存的连接，用于检查写出的响应
+    struct MemWriter {
+        buf: Rc<RefCell<Vec<u8>>>,
+    }
+
+    impl TAsyncWrite for MemWriter {
+        fn ready_to_write(&mut self) -> mini_runtime::BoxedFuture<'_, ()> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn write(&mut self, data: &[u8]) -> mini_runtime::result::Result<usize> {
+            self.buf.borrow_mut().extend_from_slice(data);
+            Ok(data.len())
+        }
+    }
+
+    #[rt_entry::rt_test]
+    async fn test_sse_forward() {
+        let buf = Rc::new(RefCell::new(Vec::new()));
+        let writer = Rc::new(AsyncMutex::new(MemWriter { buf: buf.clone() }));
+        let mut response = _ServerResponse::new(writer.into(), HttpProtocol::default());
+        let mut sse_response: SSEResponse<MemWriter> = response.chunk().await.unwrap().into();
+
+        let (tx, mut rx) = broadcast::channel(2);
+        let producer = spawn!(async move {
+            // 接收端落后，最早的两条通知被覆盖
+            for i in 0..4 {
+                tx.send(SSEProto::new(format!("notice-{}", i).into()))
+                    .unwrap();
+            }
+            sleep(time::Duration::from_millis(10)).await;
+            tx.send(SSEProto::new("notice-4".into())).unwrap();
+            // 发送端释放后forward正常结束
+        });
+
+        sse_response.forward(&mut rx).await.unwrap();
+        sse_response.close().await.unwrap();
+        producer.await.unwrap();
+
+        let body = String::from_utf8(buf.borrow().clone()).unwrap();
+        assert!(!body.contains("notice-0") && !body.contains("notice-1"));
+        for i in 2..5 {
+            assert!(body.contains(&format!("notice-{}", i)));
+        }
+        assert!(body.ends_with("0\r\n\r\n"));
+    }
+}
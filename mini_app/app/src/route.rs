@@ -1,7 +1,7 @@
 use std::{borrow::Cow, collections::HashMap, rc::Rc};
 
 use lazy_static::lazy_static;
-use mini_runtime::{ConnTimeout, UPSafeCell};
+use mini_runtime::{ConnTimeout, UPSafeCell, sync::semophore::AsyncSemophore};
 
 use crate::{HttpBoxedFuture, request::ServerRequest, response::ServerResponse};
 
@@ -15,11 +15,26 @@ pub fn add_route_handler<H: THttpMethodHandler + 'static>(
     handler: H,
     timeout: Option<ConnTimeout>,
 ) {
+    insert_route_handle(path, HttpRouteHandle::new(handler, timeout, None));
+}
+
+// 限制路由同时处理的请求数量，超出的请求按FIFO顺序排队，排队超过路由的超时时长时返回503
+pub fn add_limited_route_handler<H: THttpMethodHandler + 'static>(
+    path: Cow<'_, str>,
+    handler: H,
+    timeout: Option<ConnTimeout>,
+    max_concurrency: usize,
+) {
+    let limiter = AsyncSemophore::new(max_concurrency);
+    insert_route_handle(path, HttpRouteHandle::new(handler, timeout, Some(limiter)));
+}
+
+fn insert_route_handle(path: Cow<'_, str>, handle: Rc<HttpRouteHandle>) {
     let mut http_routes = HTTP_ROUTES.exclusive_access();
     if http_routes.contains_key(path.as_ref()) {
         panic!("route {:?} already registered", path);
     }
-    http_routes.insert(path.to_string(), HttpRouteHandle::new(handler, timeout));
+    http_routes.insert(path.to_string(), handle);
 }
 
 pub fn select_route_handler(path: &str) -> Option<Rc<HttpRouteHandle>> {
@@ -47,18 +62,32 @@ pub trait THttpMethodHandler {
 pub struct HttpRouteHandle {
     handler: Box<dyn THttpMethodHandler>,
     timeout: Option<ConnTimeout>,
+    // 并发限制，为空时不限制
+    limiter: Option<AsyncSemophore>,
 }
 
 impl HttpRouteHandle {
-    fn new<H: THttpMethodHandler + 'static>(handler: H, timeout: Option<ConnTimeout>) -> Rc<Self> {
+    fn new<H: THttpMethodHandler + 'static>(
+        handler: H,
+        timeout: Option<ConnTimeout>,
+        limiter: Option<AsyncSemophore>,
+    ) -> Rc<Self> {
         let handler: Box<dyn THttpMethodHandler> = Box::new(handler);
-        Rc::new(Self { handler, timeout })
+        Rc::new(Self {
+            handler,
+            timeout,
+            limiter,
+        })
     }
 
     pub fn get_timeout(&self) -> Option<ConnTimeout> {
         self.timeout.clone()
     }
 
+    pub fn get_limiter(&self) -> Option<&AsyncSemophore> {
+        self.limiter.as_ref()
+    }
+
     pub fn get_handler(
         &self,
         request: ServerRequest,
@@ -43,3 +43,19 @@ impl ArticalListReqBody {
         Self { articles }
     }
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NoticeReqBody {
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NoticeRespBody {
+    receivers: usize,
+}
+
+impl NoticeRespBody {
+    pub fn new(receivers: usize) -> Self {
+        Self { receivers }
+    }
+}
@@ -58,6 +58,7 @@ init_http_status! {
     (MethodNotAllowed, 405, "method not allowed"),
 
     (InternalServerError, 500, "internal server error"),
+    (ServiceUnavailable, 503, "service unavailable"),
 }
 
 // 初始化部分常见的请求方法
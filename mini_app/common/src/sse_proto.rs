@@ -18,7 +18,7 @@ const FIELD_RETRY: Field = Field::new(FieldName::Retry);
 
 const LINE_SPLITTER: &str = "\n";
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SSEProto {
     data: Field,
     event: Field,
@@ -159,7 +159,7 @@ impl Display for FieldName {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Field {
     name: FieldName,
     value: String,
@@ -10,6 +10,8 @@ use syn::{
 #[derive(Debug, Default)]
 struct EntryAttr {
     log_level: Option<String>,
+    // 以暂停的时钟运行，定时器在所有任务空闲时自动推进
+    start_paused: bool,
 }
 
 impl EntryAttr {
@@ -27,15 +29,28 @@ impl Parse for EntryAttr {
             input.parse::<Token![=]>()?;
             let value: Lit = input.parse()?;
             match key.to_string().as_ref() {
-                "log_level" => attr.log_level.replace(match value {
-                    Lit::Str(s) => s.value(),
-                    _ => {
-                        return Err(syn::Error::new_spanned(
-                            value,
-                            "log_level field must be a string",
-                        ));
-                    }
-                }),
+                "log_level" => {
+                    attr.log_level.replace(match value {
+                        Lit::Str(s) => s.value(),
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                value,
+                                "log_level field must be a string",
+                            ));
+                        }
+                    });
+                }
+                "start_paused" => {
+                    attr.start_paused = match value {
+                        Lit::Bool(b) => b.value,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                value,
+                                "start_paused field must be a bool",
+                            ));
+                        }
+                    };
+                }
                 other => {
                     return Err(syn::Error::new_spanned(
                         key,
@@ -68,15 +83,19 @@ pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
     let body = &func.block;
     let attr = parse_macro_input!(args as EntryAttr);
 
-    let import = import_stream(false);
+    let import = import_stream(false, quote! { block_on, init_logger, run, spawn });
     let logger_init = logger_init_stream(&attr);
-    let body = body_stream(body, output);
+    let body = body_stream(body, output, &attr, false, true);
+    // main的返回值交由std::process::Termination处理，返回Err时进程以非0状态码退出
     let expanded = quote! {
-        fn main() {
+        fn main() #output {
             #import
 
             #logger_init
-            #body
+            let output = #body;
+            // 等待剩余的任务执行完成
+            run();
+            output
         }
     };
 
@@ -105,12 +124,13 @@ fn _test(args: TokenStream, item: TokenStream, is_crate: bool) -> TokenStream {
 
     let attr = parse_macro_input!(args as EntryAttr);
 
-    let import = import_stream(is_crate);
+    let import = import_stream(is_crate, quote! { block_on, init_logger, spawn });
     let logger_init = logger_init_stream(&attr);
-    let body = body_stream(body, output);
+    let body = body_stream(body, output, &attr, is_crate, false);
+    // 测试函数只运行到测试体完成，返回Err或panic都会使测试失败
     let expanded = quote! {
         #[test]
-        fn #fn_name() {
+        fn #fn_name() #output {
             #import
 
             #logger_init
@@ -128,14 +148,16 @@ fn error_stream(sig: &Signature, msg: &str) -> TokenStream {
         .into()
 }
 
-fn import_stream(is_crate: bool) -> proc_macro2::TokenStream {
+fn import_stream(is_crate: bool, names: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
     if is_crate {
         quote! {
-            use crate::{init_logger, run, spawn};
+            #[allow(unused_imports)]
+            use crate::{#names};
         }
     } else {
         quote! {
-            use mini_runtime::{init_logger, run, spawn};
+            #[allow(unused_imports)]
+            use mini_runtime::{#names};
         }
     }
 }
@@ -158,7 +180,13 @@ fn logger_init_stream(entry_attr: &EntryAttr) -> proc_macro2::TokenStream {
     }
 }
 
-fn body_stream(body: &Block, output: &ReturnType) -> proc_macro2::TokenStream {
+fn body_stream(
+    body: &Block,
+    output: &ReturnType,
+    entry_attr: &EntryAttr,
+    is_crate: bool,
+    is_main: bool,
+) -> proc_macro2::TokenStream {
     let result_type = match output {
         ReturnType::Default => {
             let unit_type = Type::Tuple(syn::TypeTuple {
@@ -169,11 +197,32 @@ fn body_stream(body: &Block, output: &ReturnType) -> proc_macro2::TokenStream {
         }
         ReturnType::Type(_, ty) => ty.to_token_stream(),
     };
+    // 使用完整路径，避免与测试体中的std::time冲突
+    let pause = match (entry_attr.start_paused, is_crate) {
+        (false, _) => quote! {},
+        (true, true) => quote! { crate::time::pause(); },
+        (true, false) => quote! { mini_runtime::time::pause(); },
+    };
+    // 运行时在主体完成前被停止（如ctrl-c）时，main按被SIGINT中断的惯例以130退出，测试则直接失败
+    let unfinished = match is_main {
+        true => quote! {
+            log::warn!("runtime stopped before main finished: {}", e);
+            std::process::exit(130);
+        },
+        false => quote! {
+            panic!("runtime stopped before the test finished: {}", e);
+        },
+    };
     quote! {
-        spawn!(async { #body }, |result: #result_type| {
-            log::info!("runtime output: {:?}", result);
-        });
-
-        run();
+        {
+            #pause
+            let output: #result_type = match block_on(async #body) {
+                Ok(output) => output,
+                Err(e) => {
+                    #unfinished
+                }
+            };
+            output
+        }
     }
 }
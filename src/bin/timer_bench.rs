@@ -0,0 +1,110 @@
+use std::{
+    task::Waker,
+    time::{self, Instant},
+};
+
+use mini_runtime::{
+    collections::box_ptr_set::BoxPtrSetDropper,
+    timer::{
+        PriorityTimerQueue,
+        wheel::{TimerDropper, TimerWheel},
+    },
+};
+use rand::Rng;
+
+/*
+ * 对比最大堆与时间轮两种定时器队列
+ * 模拟大量长连接：每个连接持有一个较远的超时定时器，每次读写都会新建并取消一组超时定时器
+ */
+const CONNECTIONS: usize = 100_000;
+const ROUNDS: usize = 1_000;
+const TIMERS_PER_ROUND: usize = 300;
+
+trait TimerQueue {
+    type Dropper;
+
+    fn add_timer(&mut self, wake_at: Instant, waker: Waker) -> Self::Dropper;
+    fn get_wakers(&mut self) -> Vec<Waker>;
+    fn delay(&mut self) -> Option<time::Duration>;
+}
+
+impl TimerQueue for PriorityTimerQueue {
+    type Dropper = BoxPtrSetDropper<Waker>;
+
+    fn add_timer(&mut self, wake_at: Instant, waker: Waker) -> Self::Dropper {
+        PriorityTimerQueue::add_timer(self, wake_at, waker)
+    }
+
+    fn get_wakers(&mut self) -> Vec<Waker> {
+        PriorityTimerQueue::get_wakers(self, Instant::now())
+    }
+
+    fn delay(&mut self) -> Option<time::Duration> {
+        PriorityTimerQueue::delay(self, Instant::now())
+    }
+}
+
+impl TimerQueue for TimerWheel {
+    type Dropper = TimerDropper;
+
+    fn add_timer(&mut self, wake_at: Instant, waker: Waker) -> Self::Dropper {
+        TimerWheel::add_timer(self, wake_at, waker)
+    }
+
+    fn get_wakers(&mut self) -> Vec<Waker> {
+        TimerWheel::get_wakers(self, Instant::now())
+    }
+
+    fn delay(&mut self) -> Option<time::Duration> {
+        TimerWheel::delay(self, Instant::now())
+    }
+}
+
+fn random_deadline(rng: &mut impl Rng, max_secs: u64) -> Instant {
+    Instant::now() + time::Duration::from_millis(rng.gen_range(1000..max_secs * 1000))
+}
+
+fn bench<Q: TimerQueue>(name: &str, mut queue: Q) {
+    let mut rng = rand::thread_rng();
+
+    let start = Instant::now();
+    let keep_alive: Vec<_> = (0..CONNECTIONS)
+        .map(|_| queue.add_timer(random_deadline(&mut rng, 60), Waker::noop().clone()))
+        .collect();
+    let insert_cost = start.elapsed();
+
+    // 每轮为部分连接新建读写超时，随后读写完成并取消，再推进一次事件循环
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let droppers: Vec<_> = (0..TIMERS_PER_ROUND)
+            .map(|_| queue.add_timer(random_deadline(&mut rng, 30), Waker::noop().clone()))
+            .collect();
+        drop(droppers);
+        let _ = queue.delay();
+        let _ = queue.get_wakers();
+    }
+    let churn_cost = start.elapsed();
+
+    let start = Instant::now();
+    drop(keep_alive);
+    let cancel_cost = start.elapsed();
+
+    // 全部取消后计算下一次唤醒时间
+    let start = Instant::now();
+    let delay = queue.delay();
+    let delay_cost = start.elapsed();
+
+    println!(
+        "{:<8} insert {:>10.2?} | churn {:>10.2?} | cancel {:>10.2?} | delay after cancel {:>10.2?} ({:?})",
+        name, insert_cost, churn_cost, cancel_cost, delay_cost, delay
+    );
+}
+
+fn main() {
+    println!(
+        "{} connections, {} rounds x {} cancelled timers",
+        CONNECTIONS, ROUNDS, TIMERS_PER_ROUND
+    );
+    bench("heap", PriorityTimerQueue::default());
+    bench("wheel", TimerWheel::default());
+}
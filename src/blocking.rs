@@ -0,0 +1,143 @@
+use std::{
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{Condvar, LazyLock, Mutex},
+    thread, time,
+};
+
+use crate::runtime::remote::{RemoteJoinHandle, remote_join};
+
+// 阻塞线程池的最大线程数，超出时任务排队等待
+const MAX_BLOCKING_THREADS: usize = 64;
+// 空闲线程的存活时长
+const KEEP_ALIVE: time::Duration = time::Duration::from_secs(10);
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+static POOL: LazyLock<BlockingPool> = LazyLock::new(|| BlockingPool {
+    inner: Mutex::new(PoolInner {
+        queue: VecDeque::new(),
+        threads: 0,
+        idle: 0,
+    }),
+    cond: Condvar::new(),
+});
+
+struct PoolInner {
+    queue: VecDeque<Job>,
+    threads: usize,
+    idle: usize,
+}
+
+/*
+ * 执行同步阻塞操作（文件读写等）的线程池，进程内所有运行时共享
+ * 线程按需创建，空闲超过KEEP_ALIVE后退出
+ */
+struct BlockingPool {
+    inner: Mutex<PoolInner>,
+    cond: Condvar,
+}
+
+impl BlockingPool {
+    fn submit(&'static self, job: Job) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.queue.push_back(job);
+        if inner.queue.len() > inner.idle && inner.threads < MAX_BLOCKING_THREADS {
+            inner.threads += 1;
+            thread::Builder::new()
+                .name("mini-runtime-blocking".to_owned())
+                .spawn(|| self.work())
+                .expect("spawn blocking thread failed");
+        }
+        self.cond.notify_one();
+    }
+
+    fn work(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        loop {
+            if let Some(job) = inner.queue.pop_front() {
+                drop(inner);
+                job();
+                inner = self.inner.lock().unwrap();
+                continue;
+            }
+
+            inner.idle += 1;
+            let (guard, res) = self.cond.wait_timeout(inner, KEEP_ALIVE).unwrap();
+            inner = guard;
+            inner.idle -= 1;
+            if res.timed_out() && inner.queue.is_empty() {
+                break;
+            }
+        }
+        inner.threads -= 1;
+    }
+}
+
+/// 在阻塞线程池中执行f，避免同步io阻塞事件循环
+/// f结束后通过Poller的mio::Waker唤醒等待的任务；f发生panic时await得到错误
+pub fn spawn_blocking<F, R>(f: F) -> RemoteJoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let (completion, handle) = remote_join();
+    POOL.submit(Box::new(move || {
+        let output =
+            panic::catch_unwind(AssertUnwindSafe(f)).map_err(|_| "blocking task panicked".into());
+        completion.complete(output);
+    }));
+    handle
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        rc::Rc,
+        thread,
+        time::{self, Instant},
+    };
+
+    use crate::{blocking::spawn_blocking, result::Result, sleep, time::now, timeout};
+
+    #[rt_entry::test]
+    async fn test_spawn_blocking() -> Result<()> {
+        let ticks = Rc::new(Cell::new(0));
+        let t = ticks.clone();
+        spawn!(async move {
+            for _ in 0..5 {
+                sleep(time::Duration::from_millis(10)).await;
+                t.set(t.get() + 1);
+            }
+        });
+
+        let start = Instant::now();
+        let handle = spawn_blocking(|| {
+            thread::sleep(time::Duration::from_millis(100));
+            thread::current().name().map(String::from)
+        });
+        assert_eq!(handle.await?.as_deref(), Some("mini-runtime-blocking"));
+        assert!(start.elapsed() >= time::Duration::from_millis(100));
+        // 阻塞期间事件循环仍在执行其它任务
+        assert_eq!(ticks.get(), 5);
+
+        let handle = spawn_blocking(|| panic!("blocking panic"));
+        assert!(handle.await.is_err());
+        Ok(())
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_spawn_blocking_paused() -> Result<()> {
+        let start = now();
+        // 阻塞任务执行期间暂停的时钟不会自动推进，定时器不会提前到期
+        let handle = spawn_blocking(|| thread::sleep(time::Duration::from_millis(50)));
+        timeout(time::Duration::from_secs(1), handle).await??;
+        assert_eq!(now(), start);
+
+        // 阻塞任务结束后恢复自动推进
+        sleep(time::Duration::from_secs(1)).await;
+        assert_eq!(now() - start, time::Duration::from_secs(1));
+        Ok(())
+    }
+}
@@ -22,6 +22,10 @@ impl<T> ShareMutable<T> {
         self.0.borrow_mut()
     }
 
+    pub fn try_borrow_mut(&self) -> Option<RefMut<'_, T>> {
+        self.0.try_borrow_mut().ok()
+    }
+
     pub fn strong_count(&self) -> usize {
         Rc::strong_count(&self.0)
     }
@@ -11,18 +11,17 @@ use std::{
     time,
 };
 
-use lazy_static::lazy_static;
-
 use crate::{
-    UPSafeCell,
+    MissedTickBehavior,
+    blocking::spawn_blocking,
     dns::consts::{DNS_CACHE_FILE, DNS_CACHE_TTL},
-    err_log,
+    err_log, interval_at,
     result::Result,
     runtime::register_rt_finish_cb,
     select,
     signal::StopWaker,
-    sleep, spawn,
-    sync::mutex::AsyncMutex,
+    spawn,
+    sync::rwlock::AsyncRwLock,
     variable_log,
 };
 
@@ -33,48 +32,69 @@ pub fn open_dns_cache_refresh() {
     OPEN_DNS_CACHE_REFRESH.store(true, Ordering::Relaxed);
 }
 
-lazy_static! {
-    static ref DNS_CACHER: UPSafeCell<Rc<AsyncMutex<DNSCache>>> = {
-        let cacher = Rc::new(AsyncMutex::new(DNSCache::new().unwrap()));
+// Rc无法跨线程共享，每个线程（运行时）各自持有一份缓存
+thread_local! {
+    static DNS_CACHER: Rc<AsyncRwLock<DNSCache>> = {
+        let cacher = Rc::new(AsyncRwLock::new(DNSCache::default()));
+        spawn!(load_cache(cacher.clone()));
         if OPEN_DNS_CACHE_REFRESH.load(Ordering::Relaxed) {
             // 在要求缓存开启的情况下异步执行缓存保存操作
             spawn!(periodic_dump(cacher.clone()));
         }
         register_rt_finish_cb(Box::new(cache_dump));
-        UPSafeCell::new(cacher)
+        cacher
     };
 }
 
 pub async fn try_get_ip_from_cache(domain: &str) -> Option<IpAddr> {
-    let cacher = DNS_CACHER.exclusive_access().clone();
-    cacher.lock().await.try_get_ip(domain)
+    let cacher = DNS_CACHER.with(Rc::clone);
+    cacher.read().await.try_get_ip(domain)
 }
 
 pub async fn insert_domain_ip_map(domain: Cow<'_, str>, ip: IpAddr) {
-    let cacher = DNS_CACHER.exclusive_access().clone();
-    cacher.lock().await.insert_new_ip(domain, ip);
+    let cacher = DNS_CACHER.with(Rc::clone);
+    cacher.write().await.insert_new_ip(domain, ip);
 }
 
 // 运行时结束时进行缓存
 fn cache_dump() {
-    unsafe {
-        let _ = variable_log!(info @ DNS_CACHER.exclusive_access().get_mut().dump(), "[dns cache dump]");
+    let _ = DNS_CACHER.try_with(|cacher| unsafe {
+        variable_log!(info @ cacher.get_mut().dump(), "[dns cache dump]")
+    });
+}
+
+/*
+ * 在阻塞线程池中读取本地缓存文件，避免阻塞事件循环
+ * 读取期间持有写锁，之后的查询会等待加载完成；加载前已经开始的查询按未命中处理
+ */
+async fn load_cache(cache: Rc<AsyncRwLock<DNSCache>>) {
+    let mut cache = cache.write().await;
+    let loaded = spawn_blocking(DNSCache::read_file).await;
+    if let Ok(domain_ip_map) = err_log!(loaded.and_then(|res| res), "dns cache load failed") {
+        cache.merge(domain_ip_map);
     }
+    // 加载失败时以空缓存继续，之后的保存会覆盖无法读取的缓存文件
+    cache.loaded = true;
 }
 
 // 周期性的存储dns映射
-async fn periodic_dump(cache: Rc<AsyncMutex<DNSCache>>) {
+async fn periodic_dump(cache: Rc<AsyncRwLock<DNSCache>>) {
     let stop_waker = StopWaker::default();
     let dur = time::Duration::from_secs(30);
+    let mut ticker = interval_at(time::Instant::now() + dur, dur);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
     loop {
         select! {
             _ = stop_waker.wait() => {
                 log::info!("dns stopping");
                 return
             },
-            _ = sleep(dur) => {
+            _ = ticker.tick() => {
                 log::info!("dump dns cache when loop");
-                let _ = err_log!(cache.lock().await.dump());
+                // 文件写入放到阻塞线程池中执行，避免阻塞事件循环
+                let content = cache.read().await.dump_content();
+                let dumped = spawn_blocking(move || DNSCache::write_file(&content)).await;
+                let _ = err_log!(dumped.and_then(|res| res));
             }
         }
     }
@@ -83,17 +103,12 @@ async fn periodic_dump(cache: Rc<AsyncMutex<DNSCache>>) {
 #[derive(Default, Debug)]
 pub struct DNSCache {
     domain_ip_map: HashMap<String, CachedItem>,
+    // 本地缓存文件是否已加载，加载前不进行保存，避免覆盖缓存文件
+    loaded: bool,
 }
 
 impl DNSCache {
-    pub fn new() -> Result<Self> {
-        let mut cache = Self::default();
-        cache.load()?;
-
-        Ok(cache)
-    }
-
-    pub fn try_get_ip(&mut self, domain: &str) -> Option<IpAddr> {
+    pub fn try_get_ip(&self, domain: &str) -> Option<IpAddr> {
         if let Some(item) = self.domain_ip_map.get(domain)
             && !item.expired()
         {
@@ -108,16 +123,18 @@ impl DNSCache {
     }
 
     // 开启时从本地缓存文件中读取已缓存的映射关系
-    fn load(&mut self) -> Result<()> {
+    fn read_file() -> Result<HashMap<String, CachedItem>> {
+        let mut domain_ip_map = HashMap::new();
         let mut file = match fs::OpenOptions::new().read(true).open(DNS_CACHE_FILE) {
             Ok(file) => Ok(file),
-            Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound) => return Ok(()),
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound) => {
+                return Ok(domain_ip_map);
+            }
             Err(e) => Err(e),
         }?;
         let mut content = String::new();
         file.read_to_string(&mut content)?;
 
-        let mut domain_ip_map = HashMap::new();
         for item_str in content.split('\n') {
             if let Ok(item) = CachedItem::from_str(item_str)
                 && !item.expired()
@@ -125,26 +142,40 @@ impl DNSCache {
                 domain_ip_map.insert(item.domain.clone(), item);
             }
         }
-        self.domain_ip_map = domain_ip_map;
-        println!("map: {:?}", self.domain_ip_map);
+        log::debug!("loaded {} cached domains", domain_ip_map.len());
 
-        Ok(())
+        Ok(domain_ip_map)
+    }
+
+    // 加载期间新插入的映射更新，不被文件中的旧映射覆盖
+    fn merge(&mut self, domain_ip_map: HashMap<String, CachedItem>) {
+        for (domain, item) in domain_ip_map {
+            self.domain_ip_map.entry(domain).or_insert(item);
+        }
     }
 
     fn dump(&self) -> Result<()> {
-        let mut file = fs::OpenOptions::new()
-            .truncate(true)
-            .create(true)
-            .write(true)
-            .open(DNS_CACHE_FILE)?;
-        let content = self
-            .domain_ip_map
+        if !self.loaded {
+            return Ok(());
+        }
+        Self::write_file(&self.dump_content())
+    }
+
+    fn dump_content(&self) -> String {
+        self.domain_ip_map
             .values()
             .filter(|&item| !item.expired())
             .map(|item| format!("{}", item))
             .collect::<Vec<_>>()
-            .join("\n");
+            .join("\n")
+    }
 
+    fn write_file(content: &str) -> Result<()> {
+        let mut file = fs::OpenOptions::new()
+            .truncate(true)
+            .create(true)
+            .write(true)
+            .open(DNS_CACHE_FILE)?;
         file.write_all(content.as_bytes())?;
         Ok(())
     }
@@ -152,7 +183,8 @@ impl DNSCache {
 
 impl Drop for DNSCache {
     fn drop(&mut self) {
-        let _ = variable_log!(warn @ self.dump(), "[dns cache drop]");
+        // 缓存随线程退出而释放，此时日志等线程局部资源可能已被销毁，因此不再打印日志
+        let _ = self.dump();
     }
 }
 
@@ -74,13 +74,11 @@ async fn ip_lookup(domain: &str, record_type: QRecordType) -> Result<Vec<IpAddr>
 
 #[cfg(test)]
 mod test {
-    use std::rc::Rc;
-
     use crate::{
+        TaskScope,
         dns::{dns_parse, ip_lookup, protocol::QRecordType},
         err_log,
         result::Result,
-        sync::wait_group::{WaitGroup, WaitGroupGuard},
         variable_log,
     };
 
@@ -103,12 +101,7 @@ mod test {
 
     #[rt_entry::test(log_level = "info")]
     async fn test_dns_lookup_2() -> Result<()> {
-        let wg = Rc::new(WaitGroup::new());
-
-        let lookup = async |domain: &str, _guard: WaitGroupGuard<'_>| {
-            let _ = variable_log!(info @ dns_parse(domain, 80).await);
-        };
-
+        let scope = TaskScope::new();
         for domain in [
             "localhost",
             "ark.cn-beijing.volces.com",
@@ -116,10 +109,13 @@ mod test {
             "www.jianshu.com",
             "www.bilibili.com",
         ] {
-            spawn!(lookup(domain, wg.add()));
+            scope.spawn(async move {
+                let _ = variable_log!(info @ dns_parse(domain, 80).await);
+                Ok(())
+            });
         }
 
-        wg.wait().await;
+        scope.join().await?;
         log::info!("all domain dns parsed");
         Ok(())
     }
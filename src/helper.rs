@@ -6,6 +6,8 @@ use std::{
     time,
 };
 
+use rand::Rng;
+
 use crate::runtime::add_waker;
 
 pub struct UPSafeCell<T> {
@@ -90,6 +92,11 @@ where
     }
 }
 
+// select!随机选择开始poll的分支
+pub fn select_start(branches: usize) -> usize {
+    rand::thread_rng().gen_range(0..branches)
+}
+
 pub enum FutureResult<T> {
     Taken,
     Done(T),
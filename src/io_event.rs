@@ -4,10 +4,10 @@ use mio::Token;
 
 use crate::{
     result::Result,
-    runtime::reregister,
+    runtime::{coop::poll_proceed, dump::WaitOn, reregister, wait_on},
     task::{
         TaskAttr,
-        waker_ext::{WakerExt, WakerSet},
+        waker_ext::{WakerExt, WakerSet, WakerSetDropper},
     },
 };
 
@@ -68,12 +68,12 @@ impl IoEvent {
         IoEventHandler::new(event, self, source)
     }
 
-    // 添加等待事件的Waker
-    pub fn wait(&mut self, event: Event, waker: Waker) {
+    // 添加等待事件的Waker，dropper被释放时移除未触发的Waker
+    pub fn wait(&mut self, event: Event, waker: Waker) -> WakerSetDropper {
         match event {
-            Event::Read => self.read_wakers.add(waker.into()),
-            Event::Write => self.write_wakers.add(waker.into()),
-        };
+            Event::Read => self.read_wakers.add_with_dropper(waker.into()),
+            Event::Write => self.write_wakers.add_with_dropper(waker.into()),
+        }
     }
 
     // 事件就绪，并获取就绪的全部Waker
@@ -105,6 +105,8 @@ pub struct IoEventHandler<'a> {
     // 这里使用RefCell主要是方便在self.once时使用
     io_event: RefCell<&'a mut IoEvent>,
     once: Once,
+    // 未就绪就被释放时（如select!中未命中的分支），移除注册的Waker
+    _dropper: RefCell<Option<WakerSetDropper>>,
 }
 
 impl<'a> IoEventHandler<'a> {
@@ -118,6 +120,7 @@ impl<'a> IoEventHandler<'a> {
             event,
             io_event: RefCell::new(io_event),
             once: Once::new(),
+            _dropper: RefCell::new(None),
         })
     }
 }
@@ -129,10 +132,17 @@ impl<'a> Future for IoEventHandler<'a> {
         self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
+        // 预算耗尽时先让出执行
+        if poll_proceed(cx).is_pending() {
+            return std::task::Poll::Pending;
+        }
+
         self.once.call_once(|| {
-            self.io_event
+            let dropper = self
+                .io_event
                 .borrow_mut()
                 .wait(self.event, cx.waker().clone());
+            self._dropper.borrow_mut().replace(dropper);
         });
 
         if self
@@ -142,6 +152,10 @@ impl<'a> Future for IoEventHandler<'a> {
         {
             std::task::Poll::Ready(())
         } else {
+            wait_on(match self.event {
+                Event::Read => WaitOn::IoRead,
+                Event::Write => WaitOn::IoWrite,
+            });
             std::task::Poll::Pending
         }
     }
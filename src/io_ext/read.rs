@@ -3,7 +3,8 @@ use std::rc::Rc;
 use memchr::memmem;
 
 use crate::{
-    BoxedFuture, config::READ_BUF_SIZE, result::Result, sync::mutex::AsyncMutex, variable_log,
+    BoxedFuture, config::READ_BUF_SIZE, result::Result, runtime::coop::consume_budget,
+    sync::mutex::AsyncMutex, variable_log,
 };
 
 pub trait TAsyncRead {
@@ -28,6 +29,7 @@ pub trait TAsyncBufRead: TAsyncRead {
 
             self.ready_to_read().await?;
             loop {
+                consume_budget().await;
                 let size = self.read(&mut buf)?;
                 if size == 0 {
                     return Ok(());
@@ -1,14 +1,10 @@
 #![allow(clippy::non_canonical_partial_ord_impl)]
 #![allow(clippy::mut_from_ref)]
 
-use crate::{
-    runtime::{can_finish, get_waker, take_finish_cbs, wait},
-    signal::signal_handler,
-    timer::Sleeper,
-};
-use std::time;
+use crate::{runtime::current, timer::Sleeper};
 use std::{io::Write, pin::Pin};
 
+pub mod blocking;
 pub mod collections;
 pub mod config;
 pub mod dns;
@@ -23,14 +19,24 @@ pub(crate) mod signal;
 pub mod sync;
 pub(crate) mod task;
 pub mod tcp;
+pub mod time;
 pub(crate) mod timeout;
-pub(crate) mod timer;
+pub mod timer;
 pub mod udp;
 pub mod web;
 
+pub use blocking::spawn_blocking;
 pub use helper::{TimerRecord, UPSafeCell, take_vec_at};
-pub use task::{TaskAttr, TaskStatus};
-pub use timeout::ConnTimeout;
+pub use runtime::remote::RemoteJoinHandle;
+pub use task::{
+    TaskAttr, TaskStatus,
+    join::{JoinAll, JoinWakers, MaybeDone, TryJoinAll, join_all, try_join_all},
+    join_handle::{AbortHandle, JoinHandle},
+    local::{LocalKey, TaskLocalFuture},
+    scope::{ScopeJoin, TaskScope},
+};
+pub use timeout::{ConnTimeout, TimeoutFuture, timeout, timeout_at};
+pub use timer::interval::{Interval, MissedTickBehavior, interval, interval_at};
 
 use chrono::Local;
 use log::{Level, LevelFilter};
@@ -50,48 +56,48 @@ fn get_level_color(level: Level) -> usize {
     }
 }
 
+task_local! {
+    /// 请求id，在其scope内打印的日志会自动带上request_id
+    pub static REQUEST_ID: String;
+}
+
+// 同一进程中可能存在多个运行时（如单元测试），因此重复初始化时忽略
 pub fn init_logger(level: LevelFilter) {
-    env_logger::builder()
+    let _ = env_logger::builder()
         .filter_level(level)
         .format(|buf, record| {
+            // 不在任务中或未设置时为空
+            let request_id = REQUEST_ID
+                .try_with(|id| format!("request_id={}||", id))
+                .unwrap_or_default();
             writeln!(
                 // 这里别忘记引入std::io::Write
                 buf,
-                "\u{1B}[{}m[{} [{}] - {}:{} - {}\u{1B}[0m",
+                "\u{1B}[{}m[{} [{}] - {}:{} - {}{}\u{1B}[0m",
                 get_level_color(record.level()),
                 Local::now().format("%Y-%m-%dT%H:%M:%S.%3f"),
                 record.level(),
                 record.file().unwrap_or_default(),
                 record.line().unwrap_or_default(),
+                request_id,
                 record.args()
             )
         })
-        .init();
+        .try_init();
 }
 
-pub fn sleep(delay: time::Duration) -> Sleeper {
+pub fn sleep(delay: std::time::Duration) -> Sleeper {
     Sleeper::delay(delay)
 }
 
-// 运行直到无运行中的任务时候
+// 驱动当前线程的运行时，运行直到无运行中的任务时候
 pub fn run() {
-    signal_handler();
-    loop {
-        while let Some(waker) = get_waker() {
-            waker.wake();
-        }
-
-        // 判断是否还有多余的任务
-        if can_finish() {
-            break;
-        }
-
-        wait();
-    }
-
-    for cb in take_finish_cbs() {
-        cb();
-    }
+    current().run();
+}
 
-    log::debug!("runtime done");
+// 驱动当前线程的运行时直到fut完成，并返回fut的输出
+// 其余未完成的任务仍保留在运行时中，可以通过run()继续执行
+// 运行时在fut完成前被停止时返回ErrorType::Cancelled
+pub fn block_on<F: Future>(fut: F) -> result::Result<F::Output> {
+    current().block_on(fut)
 }
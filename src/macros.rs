@@ -1,7 +1,7 @@
 #[macro_export]
 macro_rules! spawn {
     ( $body:expr ) => {
-        $crate::runtime::spawn($body);
+        $crate::runtime::spawn($body)
     };
     ( $body:expr, $output_handle:expr ) => {
         $crate::runtime::spawn(async {
@@ -62,147 +62,227 @@ macro_rules! create_server {
 }
 
 /// 这是一个应用层的多路复用宏，具体用法暂时可见于下方的单侧用例
+/// 默认每次以随机的顺序poll各个分支，避免排在前面的分支一直优先执行；以`biased;`开头时按声明顺序poll
+/// 全部分支都已就绪但模式均不匹配时执行`else => {}`分支
+/// 分支可以使用`&mut fut`，未就绪的fut可以在下一次select!中继续使用
 #[macro_export]
 macro_rules! select {
-    ( $($pat:pat = $fut:expr => $cb:block $(|| $else_cb:block)? $(,)? )+ ) => {{
-        $crate::select!(@ $($pat = $fut => $cb $(false, $else_cb)?,)+ )
-    }};
-    // 初始匹配处理，并开始记录站位符号
-    ( @ $pat:pat = $fut:expr => $cb:block $($else:expr, $else_cb:block)?, $($branch:tt)* ) => {{
-        $crate::select!(
-            #
-            ();
-            (
-                $pat = $fut, () => $cb $($else, $else_cb)?;
-            )
-            $($branch)*
-        )
-    }};
-    // 进一步的匹配处理，$($s:tt)* 是站位符
-    ( # ( $($s:tt)* ); ( $($ready:tt)+ ) $pat:pat = $fut:expr => $cb:block $($else:expr, $else_cb:block)?, $($branch:tt)* ) => {{
+    ( @unit $($t:tt)* ) => { () };
+    // else分支，只能位于最后
+    ( @ $biased:expr; ( $($s:tt)* ); ( $($ready:tt)* ); else => $else_branch:block $(,)? ) => {
+        $crate::select!(@@ $biased; ( $($ready)* ); ( $else_branch ))
+    };
+    // 逐个处理分支，并开始记录站位符号，$($s:tt)* 是站位符
+    ( @ $biased:expr; ( $($s:tt)* ); ( $($ready:tt)* ); $pat:pat = $fut:expr => $cb:block $(|| $else_cb:block)?, $($branch:tt)* ) => {
         $crate::select!(
-            #
+            @ $biased;
             ( $($s)* _ );
             (
-                $($ready)+
-                $pat = $fut, ( $($s)* _ ) => $cb $($else, $else_cb)?;
-            )
+                $($ready)*
+                $pat = $fut, ( $($s)* ) => $cb $(false, $else_cb)?;
+            );
             $($branch)*
         )
-    }};
-    ( # ( $($s:tt)* ); ( $($pat:pat = $fut:expr, ( $($place_holder:tt)* ) => $cb:block $($else:expr, $else_cb:block)?;)+ ) ) => {{
+    };
+    ( @ $biased:expr; ( $($s:tt)* ); ( $($ready:tt)* ); $pat:pat = $fut:expr => $cb:block $(|| $else_cb:block)? $($branch:tt)* ) => {
+        $crate::select!(@ $biased; ( $($s)* ); ( $($ready)* ); $pat = $fut => $cb $(|| $else_cb)?, $($branch)*)
+    };
+    ( @ $biased:expr; ( $($s:tt)* ); ( $($ready:tt)* ); ) => {
+        $crate::select!(@@ $biased; ( $($ready)* ); ())
+    };
+    ( @@ $biased:expr; ( $($pat:pat = $fut:expr, ( $($place_holder:tt)* ) => $cb:block $($else:expr, $else_cb:block)?;)+ ); ( $($else_branch:block)? ) ) => {{
         // 如果不在这里将$async值固定下来，在poll_fn中会不断使用新的$async表达式
         // 最后增加一个`()`避免解包中的`..`的语法报错
         let mut future_with_results = ( $( $crate::helper::FutureExt::new_with_result_placeholder($fut), )+ ());
-        #[allow(unused_assignments)]
+        let branches = <[()]>::len(&[ $( $crate::select!(@unit $fut) ),+ ]);
+        // 本次select!开始poll的分支
+        let start = if $biased { 0 } else { $crate::helper::select_start(branches) };
         #[allow(irrefutable_let_patterns)]
         #[allow(unreachable_code)]
         #[allow(clippy::redundant_pattern_matching)]
-        let output = $crate::helper::poll_fn(|mut cx| {
-            // 任务索引
-            let mut idx = 0usize;
-            // 已就绪（Poll::Ready），但非预期结果的任务数量。用于全部就绪但无需要结果场景下的兜底
+        let output = $crate::helper::poll_fn(|cx| {
+            // 已就绪（Poll::Ready），但非预期结果的分支数量。用于全部就绪但无需要结果场景下的兜底
             let mut unexpected = 0usize;
-            $(
-                // 解包
-                let ( $($place_holder,)* future_with_result, ..) = &mut future_with_results;
-                let pinned = unsafe {
-                    std::pin::Pin::new_unchecked(&mut future_with_result.0)
-                };
-                match pinned.poll(&mut cx) {
-                    std::task::Poll::Ready(result) => {
-                        match result {
-                            $crate::helper::FutureResult::Taken => {
-                                unexpected += 1;
-                            }
-                            // 如果处于Pending状态，每个Future都会保留一个Waker的副本
-                            // 这里的目的是只保留一个waker可以执行，避免事件就绪后的重复驱动
-                            $crate::helper::FutureResult::Done(result) => {
-                                let mut expect: Option<bool> = None;
-                                // 判断是否符合分支要求
-                                #[allow(unused)]
-                                if matches!(&result, $pat) {
-                                    expect.replace(true);
-                                } $(else {
-                                    expect.replace($else);
-                                })?
-
-                                if let Some(expect) = expect {
-                                    // 写入结果
-                                    future_with_result.1.write(result);
-                                    let task_attr = unsafe {
-                                        $crate::TaskAttr::from_raw_data(cx.waker().data())
-                                    };
-                                    // 先取消其它waker
-                                    task_attr.update_status($crate::TaskStatus::Cancelled);
-                                    // 在重置当前waker的状态
-                                    task_attr.set_status($crate::TaskStatus::Running);
-
-                                    return std::task::Poll::Ready(Some((idx, expect)));
-                                } else {
+            for offset in 0..branches {
+                // 分支索引
+                let idx = (start + offset) % branches;
+                $(
+                    if idx == <[()]>::len(&[ $( $crate::select!(@unit $place_holder) ),* ]) {
+                        // 解包
+                        let ( $($place_holder,)* future_with_result, ..) = &mut future_with_results;
+                        let pinned = std::pin::Pin::new(&mut future_with_result.0);
+                        if let std::task::Poll::Ready(result) = pinned.poll(cx) {
+                            match result {
+                                $crate::helper::FutureResult::Taken => {
                                     unexpected += 1;
                                 }
+                                $crate::helper::FutureResult::Done(result) => {
+                                    let mut expect: Option<bool> = None;
+                                    // 判断是否符合分支要求
+                                    #[allow(unused)]
+                                    if matches!(&result, $pat) {
+                                        expect.replace(true);
+                                    } $(else {
+                                        expect.replace($else);
+                                    })?
+
+                                    if let Some(expect) = expect {
+                                        // 写入结果，未命中分支的Future随select!结束被释放，其注册的Waker由各自的dropper移除
+                                        future_with_result.1.write(result);
+                                        return std::task::Poll::Ready(Some((idx, expect)));
+                                    } else {
+                                        unexpected += 1;
+                                    }
+                                }
                             }
                         }
-                    },
-                    // 未就绪
-                    _ => {}
-                };
-                idx += 1;
-            )+
+                    }
+                )+
+            }
 
             log::debug!("all polled");
-            // 全部任务就绪，但没有符合要求的结果
-            if idx == unexpected {
+            // 全部分支就绪，但没有符合要求的结果
+            if unexpected == branches {
                 log::debug!("all branch unexpected");
                 return std::task::Poll::Ready(None);
             }
 
-            return std::task::Poll::Pending;
+            std::task::Poll::Pending
         }).await;
 
         log::trace!("select branch {:?}", output);
-        if let Some((idx, ok)) = output {
-            // 通过索引找到需要执行的代码块
-            let mut count = 0usize;
-            $(
+        match output {
+            Some((idx, ok)) => {
+                // 通过索引找到需要执行的代码块
+                $(
+                    if idx == <[()]>::len(&[ $( $crate::select!(@unit $place_holder) ),* ]) {
+                        if ok {
+                            let ( $($place_holder,)* future_with_result, ..) = future_with_results;
+                            #[allow(irrefutable_let_patterns)]
+                            if let $pat = unsafe {future_with_result.1.assume_init()} $cb
+                        } $(else $else_cb)?
+                    }
+                )+
+            }
+            None => { $( $else_branch )? }
+        }
+    }};
+    ( biased; $($branch:tt)+ ) => {
+        $crate::select!(@ true; (); (); $($branch)+)
+    };
+    ( $($branch:tt)+ ) => {
+        $crate::select!(@ false; (); (); $($branch)+)
+    };
+}
 
-                if count == idx {
-                    if ok {
-                        let ( $($place_holder,)* future_with_result, ..) = future_with_results;
-                        #[allow(irrefutable_let_patterns)]
-                        if let $pat = unsafe {future_with_result.1.assume_init()} $cb
-                    } $(else $else_cb)?
-                }
-                count += 1;
+/// 在当前任务中并发等待全部Future，按顺序以元组返回它们的输出
+/// let (a, b) = join!(fut_a, fut_b);
+/// 每个Future拥有独立的Waker，只有被唤醒的Future才会被重新poll
+#[macro_export]
+macro_rules! join {
+    ( $($fut:expr),+ $(,)? ) => {
+        $crate::join!(@ join; (); (); $($fut,)+)
+    };
+    // 为每个Future记录其在元组中的站位符号
+    ( @ $mode:ident; ( $($s:tt)* ); ( $($done:tt)* ); $fut:expr, $($rest:tt)* ) => {
+        $crate::join!(@ $mode; ( $($s)* _ ); ( $($done)* $fut, ( $($s)* ); ); $($rest)*)
+    };
+    ( @ $mode:ident; ( $($s:tt)* ); ( $($fut:expr, ( $($place_holder:tt)* ); )+ ); ) => {{
+        // 最后增加一个`()`避免解包中的`..`的语法报错
+        let mut futures = ( $( $crate::MaybeDone::new($fut), )+ () );
+        let mut wakers = $crate::JoinWakers::new(<[()]>::len(&[ $( $crate::join!(@ unit $fut) ),+ ]));
+        #[allow(unused_assignments)]
+        $crate::helper::poll_fn(|cx| {
+            wakers.register(cx);
+            let mut idx = 0usize;
+            let mut done = true;
+            $(
+                let ( $($place_holder,)* fut, .. ) = &mut futures;
+                done &= wakers.poll_child(idx, fut);
+                $crate::join!(@ check $mode fut);
+                idx += 1;
             )+
-        };
-    }}
+
+            if !done {
+                return std::task::Poll::Pending;
+            }
+            std::task::Poll::Ready($crate::join!(@ output $mode ( $({
+                let ( $($place_holder,)* fut, .. ) = &mut futures;
+                $crate::join!(@ take $mode fut)
+            },)+ )))
+        })
+        .await
+    }};
+    ( @ unit $fut:expr ) => { () };
+    ( @ check join $fut:ident ) => {};
+    ( @ check try $fut:ident ) => {
+        // 任一Future返回Err时立即返回，其余Future随之被释放
+        if let Some(e) = $fut.take_err() {
+            return std::task::Poll::Ready(Err(e));
+        }
+    };
+    ( @ take join $fut:ident ) => { $fut.take_output() };
+    ( @ take try $fut:ident ) => { $fut.take_ok() };
+    ( @ output join $output:expr ) => { $output };
+    ( @ output try $output:expr ) => { Ok($output) };
+}
+
+/// join!的Result版本，全部成功时返回Ok((a, b, ..))，否则返回第一个Err
+#[macro_export]
+macro_rules! try_join {
+    ( $($fut:expr),+ $(,)? ) => {
+        $crate::join!(@ try; (); (); $($fut,)+)
+    };
+}
+
+/// 声明任务本地变量，通过scope设置值，在scope内的任意await之间都可以通过with访问
+/// task_local! {
+///     pub static REQUEST_ID: String;
+/// }
+#[macro_export]
+macro_rules! task_local {
+    () => {};
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty; $($rest:tt)*) => {
+        $(#[$attr])*
+        $vis static $name: $crate::LocalKey<$t> = $crate::LocalKey::new(stringify!($name));
+        $crate::task_local!($($rest)*);
+    };
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty) => {
+        $crate::task_local!($(#[$attr])* $vis static $name: $t;);
+    };
 }
 
 #[cfg(test)]
 mod test {
-    use std::time;
+    use std::{cell::Cell, rc::Rc, time};
 
     use crate::{
+        helper::{poll_fn, yield_here},
         result::{ErrorType, Result},
         sleep,
+        sync::notifier::Notifier,
+        tcp::listener::Listener,
+        time::now,
     };
 
-    #[rt_entry::test]
+    #[rt_entry::test(start_paused = true)]
     async fn test_select_1() {
-        let start_at = time::Instant::now();
+        let start_at = now();
 
+        let mut branch = 0;
         select!(
             _ = sleep(time::Duration::from_millis(200)) => {
                 log::info!("in 200ms branch");
+                branch = 200;
             },
             _ = sleep(time::Duration::from_millis(100)) => {
                 log::info!("in 100ms branch");
+                branch = 100;
             }
         );
 
-        log::info!("total cost {}ms", start_at.elapsed().as_millis());
+        assert_eq!(branch, 100);
+        assert_eq!(now() - start_at, time::Duration::from_millis(100));
     }
 
     /*
@@ -297,4 +377,108 @@ mod test {
             }
         }
     }
+
+    #[rt_entry::test]
+    async fn test_select_biased() {
+        // 多个分支同时就绪时，biased按声明顺序选择
+        for _ in 0..10 {
+            let mut branch = 0;
+            select! {
+                biased;
+                _ = async {} => { branch = 1; },
+                _ = async {} => { branch = 2; },
+            }
+            assert_eq!(branch, 1);
+        }
+
+        // 默认随机选择，后面的分支不会被饿死
+        let mut hits = [0; 3];
+        for _ in 0..100 {
+            select! {
+                _ = async {} => { hits[0] += 1; },
+                _ = async {} => { hits[1] += 1; },
+                _ = async {} => { hits[2] += 1; },
+            }
+        }
+        assert!(hits.iter().all(|&n| n > 0));
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_select_else() {
+        async fn a(dur: time::Duration) -> Option<()> {
+            sleep(dur).await;
+            None
+        }
+
+        let start_at = now();
+        let mut branch = 0;
+        select! {
+            Some(_) = a(time::Duration::from_millis(200)) => { branch = 1; },
+            Some(_) = a(time::Duration::from_millis(100)) => { branch = 2; },
+            else => { branch = 3; }
+        }
+        assert_eq!(branch, 3);
+        assert_eq!(now() - start_at, time::Duration::from_millis(200));
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_select_reuse() {
+        let start_at = now();
+        // 同一个fut在多次select!之间复用
+        let mut deadline = std::pin::pin!(sleep(time::Duration::from_millis(100)));
+        let mut ticks = 0;
+        loop {
+            select! {
+                _ = &mut deadline => {
+                    break;
+                },
+                _ = sleep(time::Duration::from_millis(30)) => {
+                    ticks += 1;
+                }
+            }
+        }
+        assert_eq!(ticks, 3);
+        assert_eq!(now() - start_at, time::Duration::from_millis(100));
+    }
+
+    /*
+     * 未命中分支的Future被释放后，其注册的Waker不会再唤醒任务
+     */
+    #[rt_entry::test(start_paused = true)]
+    async fn test_select_no_stale_wakeup() {
+        let notifier = Rc::new(Notifier::new());
+        let mut listener = Listener::new("127.0.0.1", 0).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let polls = Rc::new(Cell::new(0));
+
+        let (n, p) = (notifier.clone(), polls.clone());
+        let handle = spawn!(async move {
+            let mut fut = std::pin::pin!(async move {
+                select! {
+                    biased;
+                    _ = listener.ready() => {},
+                    _ = n.wait() => {},
+                    _ = sleep(time::Duration::from_millis(50)) => {},
+                    _ = spawn!(sleep(time::Duration::from_millis(20))) => {},
+                    _ = async {} => {},
+                }
+                sleep(time::Duration::from_secs(10)).await;
+            });
+            poll_fn(|cx| {
+                p.set(p.get() + 1);
+                fut.as_mut().poll(cx)
+            })
+            .await
+        });
+        yield_here().await;
+        assert_eq!(polls.get(), 1);
+
+        // 触发全部未命中分支曾经等待的事件
+        notifier.notify_all();
+        let _stream = std::net::TcpStream::connect(addr).unwrap();
+        sleep(time::Duration::from_millis(100)).await;
+        assert_eq!(polls.get(), 1);
+
+        handle.abort();
+    }
 }
@@ -1,45 +1,99 @@
-use std::time;
+use std::{collections::HashSet, time};
 
 use std::task::Waker;
 
 use crate::{
-    collections::box_ptr_set::BoxPtrSetDropper,
     io_event::{Event, IoEvent},
     result::Result,
-    timer::PriorityTimerQueue,
+    runtime::remote::{REMOTE_TOKEN, RemoteHandle},
+    signal::{SIGNAL_TOKEN, SignalPipe},
+    time::Clock,
+    timer::wheel::{TimerDropper, TimerWheel},
 };
 
 pub struct Poller {
-    timer_queue: PriorityTimerQueue,
+    timer_queue: TimerWheel,
     net_poll: mio::Poll,
+    events: mio::event::Events,
+    remote: RemoteHandle,
+    signal_pipe: Option<SignalPipe>,
+    // 已注册的io的token，只有注册成功的io在注销时才会被移除
+    sources: HashSet<mio::Token>,
 }
 
 impl Poller {
-    pub fn new() -> Result<Self> {
+    // 定时器以clock的起点为第0个tick，保证暂停的时钟推进到定时器时恰好到期
+    pub(crate) fn new(event_capacity: usize, handle_signal: bool, clock: &Clock) -> Result<Self> {
+        let net_poll = mio::Poll::new()?;
+        let remote = RemoteHandle::new(net_poll.registry())?;
+        let signal_pipe = match handle_signal {
+            true => Some(SignalPipe::new(net_poll.registry())?),
+            false => None,
+        };
         Ok(Self {
-            timer_queue: PriorityTimerQueue::default(),
-            net_poll: mio::Poll::new()?,
+            timer_queue: TimerWheel::new(clock.origin()),
+            net_poll,
+            events: mio::event::Events::with_capacity(event_capacity),
+            remote,
+            signal_pipe,
+            sources: HashSet::new(),
         })
     }
 
-    pub fn poll(&mut self) -> Vec<Waker> {
-        let mut wakers = Vec::new();
-        let delay = self.timer_queue.delay();
+    pub fn remote(&self) -> RemoteHandle {
+        self.remote.clone()
+    }
 
-        wakers.extend(self.io_poll(delay));
-        wakers.extend(self.timer_queue.get_wakers());
+    // block为false时只获取已就绪的事件，不会阻塞等待
+    pub(crate) fn poll(&mut self, block: bool, clock: &Clock) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+        let delay = if block {
+            self.timer_queue.delay(clock.now())
+        } else {
+            Some(time::Duration::ZERO)
+        };
+
+        match delay {
+            // 其它线程上还有未结束的工作（如spawn_blocking）时不推进暂停的时钟，等待其完成的唤醒
+            Some(_) if clock.is_paused() && block && self.remote.busy() => {
+                wakers.extend(self.io_poll(None));
+            }
+            // 时钟暂停时不等待定时器，没有已就绪的事件时直接将时钟推进到最近的定时器
+            Some(delay) if clock.is_paused() => {
+                wakers.extend(self.io_poll(Some(time::Duration::ZERO)));
+                if self.events.is_empty() {
+                    clock.advance(delay);
+                }
+            }
+            _ => wakers.extend(self.io_poll(delay)),
+        }
+        wakers.extend(self.timer_queue.get_wakers(clock.now()));
 
         wakers
     }
 
+    pub fn poll_timers(&mut self, now: time::Instant) -> Vec<Waker> {
+        self.timer_queue.get_wakers(now)
+    }
+
     fn io_poll(&mut self, timeout: Option<time::Duration>) -> Vec<Waker> {
         log::trace!("net_poll timeout: {:?}", timeout);
 
         let mut wakers = Vec::new();
-        let mut events = mio::event::Events::with_capacity(1024);
-        match self.net_poll.poll(&mut events, timeout) {
+        match self.net_poll.poll(&mut self.events, timeout) {
             Ok(()) => {
-                for e in events.iter() {
+                for e in self.events.iter() {
+                    // 远程唤醒只用于打断阻塞，投递的Waker由运行时自行取出
+                    if e.token() == REMOTE_TOKEN {
+                        continue;
+                    }
+                    // 信号只需要打断阻塞，由运行时在事件循环中处理
+                    if e.token() == SIGNAL_TOKEN {
+                        if let Some(signal_pipe) = self.signal_pipe.as_mut() {
+                            signal_pipe.drain();
+                        }
+                        continue;
+                    }
                     let io_event = unsafe { IoEvent::from_token(e.token()) };
                     wakers.extend(io_event.read_events(e));
                 }
@@ -52,10 +106,15 @@ impl Poller {
         wakers
     }
 
-    pub fn add_timer(&mut self, wake_at: time::Instant, waker: Waker) -> BoxPtrSetDropper<Waker> {
+    pub fn add_timer(&mut self, wake_at: time::Instant, waker: Waker) -> TimerDropper {
         self.timer_queue.add_timer(wake_at, waker)
     }
 
+    // 取出全部未触发的定时器，用于运行时释放时的清理
+    pub fn take_timers(&mut self) -> Vec<Waker> {
+        self.timer_queue.take_all()
+    }
+
     pub fn register<S: mio::event::Source>(
         &mut self,
         events: Vec<crate::io_event::Event>,
@@ -67,6 +126,7 @@ impl Poller {
             self.net_poll
                 .registry()
                 .register(source, io_event.token(), interests)?;
+            self.sources.insert(io_event.token());
         }
 
         Ok(())
@@ -87,7 +147,21 @@ impl Poller {
         Ok(())
     }
 
-    pub fn deregister<S: mio::event::Source>(&mut self, source: &mut S) -> Result<()> {
-        Ok(self.net_poll.registry().deregister(source)?)
+    pub fn deregister<S: mio::event::Source>(
+        &mut self,
+        io_event: &IoEvent,
+        source: &mut S,
+    ) -> Result<()> {
+        self.net_poll.registry().deregister(source)?;
+        self.sources.remove(&io_event.token());
+        Ok(())
+    }
+
+    pub fn sources(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn timers(&self) -> usize {
+        self.timer_queue.len()
     }
 }
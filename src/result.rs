@@ -27,6 +27,22 @@ impl Error {
     pub fn is_eof(&self) -> bool {
         matches!(self.type_, ErrorType::Eof)
     }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.type_, ErrorType::Timeout)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.type_, ErrorType::Cancelled)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        matches!(self.type_, ErrorType::Closed)
+    }
+
+    pub fn is_lagged(&self) -> bool {
+        matches!(self.type_, ErrorType::Lagged(_))
+    }
 }
 
 impl Display for Error {
@@ -46,6 +62,11 @@ pub enum ErrorType {
     Eof,
     Blocked,
     Timeout,
+    Cancelled,
+    // 通道等资源已关闭
+    Closed,
+    // 广播通道的接收端落后，被覆盖而跳过的数据数量
+    Lagged(u64),
     ReadTimeout,
     WriteTimeout,
     IoError(io::Error),
@@ -0,0 +1,113 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::runtime::{add_waker, current};
+
+// 任务单次poll中可执行的io操作数量，耗尽后强制让出执行
+pub(crate) const BUDGET: usize = 128;
+
+/*
+ * 协作式调度的预算：每次poll任务前重置，io等资源每次操作消耗一个
+ * 预算耗尽时资源返回Pending并将任务重新加入就绪队列，避免一直有数据可读的连接长期占用事件循环
+ */
+pub(crate) fn poll_proceed(cx: &mut Context<'_>) -> Poll<()> {
+    let rt = current();
+    match rt.0._budget.get() {
+        // 不在任务的poll中时不受限制
+        None => Poll::Ready(()),
+        Some(0) => {
+            add_waker(cx.waker().clone());
+            Poll::Pending
+        }
+        Some(n) => {
+            rt.0._budget.set(Some(n - 1));
+            Poll::Ready(())
+        }
+    }
+}
+
+/// 消耗一个预算，预算耗尽时让出执行。可在计算密集的循环中调用，避免饿死其它任务
+pub fn consume_budget() -> ConsumeBudget {
+    ConsumeBudget {}
+}
+
+pub struct ConsumeBudget {}
+
+impl Future for ConsumeBudget {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        poll_proceed(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, time};
+
+    use crate::{
+        helper::yield_here,
+        runtime::{
+            coop::{BUDGET, consume_budget},
+            current,
+        },
+        sleep,
+        time::now,
+    };
+
+    #[rt_entry::test]
+    async fn test_budget() {
+        let ran = Rc::new(Cell::new(0));
+        let r = ran.clone();
+        let busy = spawn!(async move {
+            // 预算耗尽前不会让出，耗尽后其它任务得以执行
+            let mut seen = Vec::new();
+            for _ in 0..BUDGET * 2 + 1 {
+                consume_budget().await;
+                seen.push(r.get());
+            }
+            seen
+        });
+        let r = ran.clone();
+        spawn!(async move {
+            loop {
+                r.set(r.get() + 1);
+                yield_here().await;
+            }
+        })
+        .detach();
+
+        let seen = busy.await.unwrap();
+        assert!(seen[..BUDGET].iter().all(|&n| n == seen[0]));
+        assert!(seen[BUDGET] > seen[0]);
+        assert!(seen[BUDGET * 2] > seen[BUDGET]);
+    }
+
+    #[rt_entry::test]
+    async fn test_nested_budget() {
+        consume_budget().await;
+        // 嵌套驱动同一个运行时，内层任务的poll不影响外层任务剩余的预算
+        block_on(async { consume_budget().await }).unwrap();
+        assert_eq!(current().0._budget.get(), Some(BUDGET - 1));
+    }
+
+    #[rt_entry::test]
+    async fn test_event_interval() {
+        let fired = Rc::new(Cell::new(false));
+        let f = fired.clone();
+        spawn!(async move {
+            sleep(time::Duration::from_millis(10)).await;
+            f.set(true);
+        })
+        .detach();
+
+        // 就绪队列一直不为空时，定时器仍能被处理
+        let start = now();
+        while !fired.get() && now() - start < time::Duration::from_secs(1) {
+            yield_here().await;
+        }
+        assert!(fired.get());
+    }
+}
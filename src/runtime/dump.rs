@@ -0,0 +1,214 @@
+use std::{fmt::Display, panic::Location, time};
+
+/// 任务被挂起时等待的对象
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WaitOn {
+    // 定时器及其到期时间
+    Timer(time::Instant),
+    IoRead,
+    IoWrite,
+    Mutex,
+    RwLock,
+    Notifier,
+    Semaphore,
+    Channel,
+    // 等待其它任务结束
+    Join,
+}
+
+impl Display for WaitOn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WaitOn::Timer(wake_at) => write!(
+                f,
+                "timer(in {:?})",
+                wake_at.saturating_duration_since(crate::time::now())
+            ),
+            WaitOn::IoRead => write!(f, "io read"),
+            WaitOn::IoWrite => write!(f, "io write"),
+            WaitOn::Mutex => write!(f, "mutex"),
+            WaitOn::RwLock => write!(f, "rwlock"),
+            WaitOn::Notifier => write!(f, "notifier"),
+            WaitOn::Semaphore => write!(f, "semaphore"),
+            WaitOn::Channel => write!(f, "channel"),
+            WaitOn::Join => write!(f, "join"),
+        }
+    }
+}
+
+// 运行时为每个运行中的任务保存的调试信息
+pub(crate) struct TaskInfo {
+    name: Option<String>,
+    location: &'static Location<'static>,
+    spawned_at: time::Instant,
+    // 最近一次poll返回Pending时等待的对象，每次poll前清空
+    waiting: Vec<WaitOn>,
+}
+
+impl TaskInfo {
+    pub(crate) fn new(name: Option<String>, location: &'static Location<'static>) -> Self {
+        Self {
+            name,
+            location,
+            spawned_at: crate::time::now(),
+            waiting: Vec::new(),
+        }
+    }
+
+    pub(crate) fn clear_waiting(&mut self) {
+        self.waiting.clear();
+    }
+
+    pub(crate) fn wait_on(&mut self, reason: WaitOn) {
+        self.waiting.push(reason);
+    }
+
+    pub(crate) fn dump(&self, id: usize, polling: bool) -> TaskDump {
+        let state = match (polling, self.waiting.is_empty()) {
+            (true, _) => TaskState::Running,
+            (false, false) => TaskState::Waiting,
+            (false, true) => TaskState::Idle,
+        };
+        TaskDump {
+            id,
+            name: self.name.clone(),
+            location: self.location,
+            state,
+            age: crate::time::now().saturating_duration_since(self.spawned_at),
+            waiting: self.waiting.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    // 正在被poll
+    Running,
+    // 挂起在已知的对象上
+    Waiting,
+    // 已就绪等待执行，或挂起在未记录的对象上（如其它线程的唤醒）
+    Idle,
+}
+
+/// 单个任务的快照
+#[derive(Debug, Clone)]
+pub struct TaskDump {
+    id: usize,
+    name: Option<String>,
+    location: &'static Location<'static>,
+    state: TaskState,
+    age: time::Duration,
+    waiting: Vec<WaitOn>,
+}
+
+impl TaskDump {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // 调用spawn的位置
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    pub fn state(&self) -> TaskState {
+        self.state
+    }
+
+    pub fn age(&self) -> time::Duration {
+        self.age
+    }
+
+    pub fn waiting(&self) -> &[WaitOn] {
+        &self.waiting
+    }
+
+    // 任务的名称与提交位置
+    pub(crate) fn describe(&self) -> String {
+        format!(
+            "task={} name={} at {}",
+            self.id,
+            self.name.as_deref().unwrap_or("-"),
+            self.location
+        )
+    }
+}
+
+impl Display for TaskDump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {:?} age={:?}", self.describe(), self.state, self.age)?;
+        if !self.waiting.is_empty() {
+            let waiting: Vec<_> = self.waiting.iter().map(WaitOn::to_string).collect();
+            write!(f, " waiting on [{}]", waiting.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, time};
+
+    use crate::{
+        runtime::{
+            TaskBuilder,
+            dump::{TaskState, WaitOn},
+            dump_tasks, spawn_named,
+        },
+        sleep,
+        sync::{mutex::AsyncMutex, notifier::Notifier},
+        time::advance,
+    };
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_dump_tasks() {
+        let mtx = Rc::new(AsyncMutex::new(()));
+        let notifier = Rc::new(Notifier::new());
+        let guard = mtx.lock().await;
+
+        spawn_named("sleeper", sleep(time::Duration::from_secs(5))).detach();
+        let m = mtx.clone();
+        TaskBuilder::new()
+            .name("locker")
+            .spawn(async move {
+                let _guard = m.lock().await;
+            })
+            .detach();
+        let n = notifier.clone();
+        spawn!(async move { n.wait().await }).detach();
+        // 让新任务执行到挂起
+        crate::helper::yield_here().await;
+        // 任务的存活时长使用运行时的时钟
+        advance(time::Duration::from_secs(1)).await;
+
+        let dumps = dump_tasks();
+        assert_eq!(dumps.len(), 4);
+        let main = dumps
+            .iter()
+            .find(|d| d.state() == TaskState::Running)
+            .unwrap();
+        assert!(main.name().is_none());
+
+        let sleeper = dumps.iter().find(|d| d.name() == Some("sleeper")).unwrap();
+        assert_eq!(sleeper.state(), TaskState::Waiting);
+        assert!(matches!(sleeper.waiting(), [WaitOn::Timer(_)]));
+        assert_eq!(sleeper.location().file(), file!());
+        assert_eq!(sleeper.age(), time::Duration::from_secs(1));
+
+        let locker = dumps.iter().find(|d| d.name() == Some("locker")).unwrap();
+        assert_eq!(locker.waiting(), &[WaitOn::Mutex]);
+        // spawn!记录的是宏的调用位置
+        let waiter = dumps
+            .iter()
+            .find(|d| d.waiting() == [WaitOn::Notifier])
+            .unwrap();
+        assert_eq!(waiter.location().file(), file!());
+
+        drop(guard);
+        notifier.notify_all();
+    }
+}
@@ -0,0 +1,329 @@
+use std::{collections::HashMap, time};
+
+use crate::task::task_id::TaskId;
+
+// 直方图的桶数，第i个桶统计耗时在[2^(i-1), 2^i)微秒的poll，最后一个桶统计更长的poll
+const HISTOGRAM_BUCKETS: usize = 20;
+
+/// 单次poll耗时的直方图，按微秒以2的幂分桶
+#[derive(Debug, Clone, Default)]
+pub struct PollHistogram {
+    buckets: [u64; HISTOGRAM_BUCKETS],
+}
+
+impl PollHistogram {
+    fn record(&mut self, dur: time::Duration) {
+        let us = dur.as_micros() as u64;
+        let index = (64 - us.leading_zeros() as usize).min(HISTOGRAM_BUCKETS - 1);
+        self.buckets[index] += 1;
+    }
+
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    // 第index个桶统计的耗时范围，最后一个桶没有上限
+    pub fn bucket_range(index: usize) -> (time::Duration, Option<time::Duration>) {
+        let low = match index {
+            0 => 0,
+            _ => 1 << (index - 1),
+        };
+        let high = (index < HISTOGRAM_BUCKETS - 1).then(|| time::Duration::from_micros(1 << index));
+        (time::Duration::from_micros(low), high)
+    }
+}
+
+/// 单个任务的poll统计
+#[derive(Debug, Clone, Default)]
+pub struct TaskMetrics {
+    id: usize,
+    polls: u64,
+    total_poll_time: time::Duration,
+    max_poll_time: time::Duration,
+    histogram: Option<PollHistogram>,
+}
+
+impl TaskMetrics {
+    fn record(&mut self, dur: time::Duration) {
+        self.polls += 1;
+        self.total_poll_time += dur;
+        self.max_poll_time = self.max_poll_time.max(dur);
+        if let Some(histogram) = self.histogram.as_mut() {
+            histogram.record(dur);
+        }
+    }
+
+    // 任务id，只在所属线程内唯一
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    pub fn total_poll_time(&self) -> time::Duration {
+        self.total_poll_time
+    }
+
+    pub fn avg_poll_time(&self) -> time::Duration {
+        avg(self.total_poll_time, self.polls)
+    }
+
+    pub fn max_poll_time(&self) -> time::Duration {
+        self.max_poll_time
+    }
+
+    // 只有通过Builder::enable_poll_histogram开启后才会统计
+    pub fn histogram(&self) -> Option<&PollHistogram> {
+        self.histogram.as_ref()
+    }
+}
+
+fn avg(total: time::Duration, count: u64) -> time::Duration {
+    match count {
+        0 => time::Duration::ZERO,
+        _ => total.div_f64(count as f64),
+    }
+}
+
+/*
+ * 运行时内部的统计数据，随任务的poll与事件循环实时更新
+ * 任务结束后其统计数据只保留在汇总值中
+ */
+#[derive(Default)]
+pub(crate) struct MetricsRecorder {
+    poll_histogram: bool,
+    tasks: HashMap<TaskId, TaskMetrics>,
+    polls: u64,
+    total_poll_time: time::Duration,
+    max_poll_time: time::Duration,
+    slow_polls: u64,
+    loop_iterations: u64,
+    total_loop_time: time::Duration,
+    last_loop_latency: time::Duration,
+    max_loop_latency: time::Duration,
+}
+
+impl MetricsRecorder {
+    pub(crate) fn new(poll_histogram: bool) -> Self {
+        Self {
+            poll_histogram,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn record_poll(&mut self, tid: &TaskId, dur: time::Duration) {
+        self.polls += 1;
+        self.total_poll_time += dur;
+        self.max_poll_time = self.max_poll_time.max(dur);
+
+        let poll_histogram = self.poll_histogram;
+        self.tasks
+            .entry(tid.clone())
+            .or_insert_with(|| TaskMetrics {
+                id: tid.value(),
+                histogram: poll_histogram.then(PollHistogram::default),
+                ..Default::default()
+            })
+            .record(dur);
+    }
+
+    pub(crate) fn record_slow_poll(&mut self) {
+        self.slow_polls += 1;
+    }
+
+    pub(crate) fn remove_task(&mut self, tid: &TaskId) {
+        self.tasks.remove(tid);
+    }
+
+    pub(crate) fn record_loop(&mut self, dur: time::Duration) {
+        self.loop_iterations += 1;
+        self.total_loop_time += dur;
+        self.last_loop_latency = dur;
+        self.max_loop_latency = self.max_loop_latency.max(dur);
+    }
+
+    pub(crate) fn snapshot(&self, live: LiveCounts) -> RuntimeMetrics {
+        let mut tasks: Vec<_> = self.tasks.values().cloned().collect();
+        tasks.sort_by_key(|t| t.id);
+        RuntimeMetrics {
+            live,
+            polls: self.polls,
+            total_poll_time: self.total_poll_time,
+            max_poll_time: self.max_poll_time,
+            slow_polls: self.slow_polls,
+            loop_iterations: self.loop_iterations,
+            total_loop_time: self.total_loop_time,
+            last_loop_latency: self.last_loop_latency,
+            max_loop_latency: self.max_loop_latency,
+            tasks,
+        }
+    }
+}
+
+// 获取快照时运行时的实时状态
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LiveCounts {
+    pub(crate) tasks: usize,
+    pub(crate) ready_wakers: usize,
+    pub(crate) io_sources: usize,
+    pub(crate) timers: usize,
+    pub(crate) loop_stalls: usize,
+}
+
+/// 运行时统计数据的快照
+#[derive(Debug, Clone)]
+pub struct RuntimeMetrics {
+    live: LiveCounts,
+    polls: u64,
+    total_poll_time: time::Duration,
+    max_poll_time: time::Duration,
+    slow_polls: u64,
+    loop_iterations: u64,
+    total_loop_time: time::Duration,
+    last_loop_latency: time::Duration,
+    max_loop_latency: time::Duration,
+    tasks: Vec<TaskMetrics>,
+}
+
+impl RuntimeMetrics {
+    // 运行中的任务数量
+    pub fn live_tasks(&self) -> usize {
+        self.live.tasks
+    }
+
+    // 就绪队列中等待执行的Waker数量
+    pub fn ready_queue_depth(&self) -> usize {
+        self.live.ready_wakers
+    }
+
+    // 已注册到Poller的io数量
+    pub fn io_sources(&self) -> usize {
+        self.live.io_sources
+    }
+
+    // 未触发的定时器数量
+    pub fn timers(&self) -> usize {
+        self.live.timers
+    }
+
+    // 运行时启动以来全部任务的poll次数
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    pub fn total_poll_time(&self) -> time::Duration {
+        self.total_poll_time
+    }
+
+    pub fn avg_poll_time(&self) -> time::Duration {
+        avg(self.total_poll_time, self.polls)
+    }
+
+    pub fn max_poll_time(&self) -> time::Duration {
+        self.max_poll_time
+    }
+
+    // 超过Builder::slow_poll_threshold的poll次数
+    pub fn slow_polls(&self) -> u64 {
+        self.slow_polls
+    }
+
+    // 看门狗检测到的事件循环卡顿次数
+    pub fn loop_stalls(&self) -> usize {
+        self.live.loop_stalls
+    }
+
+    // 事件循环的轮数，每轮执行完当前全部就绪的Waker
+    pub fn loop_iterations(&self) -> u64 {
+        self.loop_iterations
+    }
+
+    pub fn last_loop_latency(&self) -> time::Duration {
+        self.last_loop_latency
+    }
+
+    pub fn avg_loop_latency(&self) -> time::Duration {
+        avg(self.total_loop_time, self.loop_iterations)
+    }
+
+    pub fn max_loop_latency(&self) -> time::Duration {
+        self.max_loop_latency
+    }
+
+    // 运行中且至少被poll过一次的任务，按任务id排序
+    pub fn tasks(&self) -> &[TaskMetrics] {
+        &self.tasks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time;
+
+    use crate::{
+        runtime::{Runtime, metrics, metrics::PollHistogram, spawn},
+        sleep,
+        tcp::listener::Listener,
+    };
+
+    #[test]
+    fn test_runtime_metrics() {
+        let rt = Runtime::builder()
+            .handle_signal(false)
+            .enable_poll_histogram(true)
+            .build()
+            .unwrap();
+
+        let metrics = rt
+            .block_on(async {
+                let busy = spawn(async {
+                    for _ in 0..3 {
+                        std::thread::sleep(time::Duration::from_millis(5));
+                        sleep(time::Duration::from_millis(1)).await;
+                    }
+                });
+                let idle = spawn(sleep(time::Duration::from_secs(10)));
+                busy.await.unwrap();
+
+                let metrics = metrics();
+                idle.abort();
+                metrics
+            })
+            .unwrap();
+
+        // 主任务与idle任务仍在运行
+        assert_eq!(metrics.live_tasks(), 2);
+        assert_eq!(metrics.timers(), 1);
+        assert!(metrics.polls() >= 6);
+        assert!(metrics.max_poll_time() >= time::Duration::from_millis(5));
+        assert!(metrics.loop_iterations() > 0);
+        assert!(metrics.max_loop_latency() >= time::Duration::from_millis(5));
+        assert_eq!(metrics.tasks().len(), 2);
+        for task in metrics.tasks() {
+            let histogram = task.histogram().unwrap();
+            assert_eq!(histogram.buckets().iter().sum::<u64>(), task.polls());
+        }
+
+        // 结束的任务不再保留
+        assert_eq!(rt.metrics().live_tasks(), 0);
+        assert!(rt.metrics().tasks().is_empty());
+
+        let (low, high) = PollHistogram::bucket_range(3);
+        assert_eq!(low, time::Duration::from_micros(4));
+        assert_eq!(high, Some(time::Duration::from_micros(8)));
+    }
+
+    #[rt_entry::test]
+    async fn test_io_sources() {
+        let listener1 = Listener::new("127.0.0.1", 0).unwrap();
+        let listener2 = Listener::new("127.0.0.1", 0).unwrap();
+        assert_eq!(metrics().io_sources(), 2);
+
+        drop(listener1);
+        assert_eq!(metrics().io_sources(), 1);
+        drop(listener2);
+        assert_eq!(metrics().io_sources(), 0);
+    }
+}
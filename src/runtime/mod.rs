@@ -0,0 +1,794 @@
+use std::{
+    cell::{Cell, RefCell, RefMut},
+    collections::{HashMap, VecDeque},
+    panic::Location,
+    rc::{Rc, Weak},
+    task::Waker,
+    time,
+};
+
+pub mod coop;
+pub mod dump;
+pub mod metrics;
+pub mod multi_thread;
+pub(crate) mod remote;
+mod watchdog;
+
+use crate::{
+    helper::UPSafeCell,
+    io_event::IoEvent,
+    poller::Poller,
+    result::{ErrorType, Result},
+    runtime::{
+        dump::{TaskDump, TaskInfo, WaitOn},
+        metrics::{LiveCounts, MetricsRecorder, RuntimeMetrics},
+        remote::RemoteHandle,
+        watchdog::Watchdog,
+    },
+    signal::{self, dump_count, signal_count, stopped},
+    task::{
+        TTaskClear, Task, TaskAttr, join_handle::JoinHandle, task_id::TaskId, waker_ext::WakerSet,
+    },
+    time::Clock,
+    timer::wheel::TimerDropper,
+    variable_log,
+};
+
+thread_local! {
+    // 当前线程正在驱动的运行时。未显式进入任何运行时的时候会懒加载一个默认运行时
+    static CURRENT: RefCell<Option<Runtime>> = const { RefCell::new(None) };
+}
+
+type FinishCb = Box<dyn FnOnce() + 'static>;
+
+#[derive(Clone)]
+pub struct Builder {
+    // 单次io poll最多获取的事件数量
+    event_capacity: usize,
+    // 每轮事件循环最多执行的就绪Waker数量，超出后先处理io与定时器
+    event_interval: usize,
+    // 是否响应SIGINT/SIGTERM
+    handle_signal: bool,
+    // 收到停止信号后的最大等待时长
+    grace_period: time::Duration,
+    // 多线程模式下的工作线程数量
+    worker_threads: usize,
+    // 是否以暂停的时钟启动，用于定时器相关的确定性测试
+    start_paused: bool,
+    // 是否统计每个任务poll耗时的直方图
+    poll_histogram: bool,
+    // 单次poll超过该时长时打印告警
+    slow_poll_threshold: Option<time::Duration>,
+    // 事件循环卡顿超过该时长时由看门狗线程打印告警
+    watchdog: Option<time::Duration>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Self {
+            event_capacity: 1024,
+            event_interval: 256,
+            handle_signal: true,
+            grace_period: time::Duration::from_millis(1000),
+            worker_threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            start_paused: false,
+            poll_histogram: false,
+            slow_poll_threshold: None,
+            watchdog: None,
+        }
+    }
+
+    pub fn event_capacity(mut self, capacity: usize) -> Self {
+        self.event_capacity = capacity;
+        self
+    }
+
+    pub fn event_interval(mut self, n: usize) -> Self {
+        assert!(n > 0, "event_interval must be greater than 0");
+        self.event_interval = n;
+        self
+    }
+
+    pub fn handle_signal(mut self, enable: bool) -> Self {
+        self.handle_signal = enable;
+        self
+    }
+
+    pub fn grace_period(mut self, dur: time::Duration) -> Self {
+        self.grace_period = dur;
+        self
+    }
+
+    pub fn worker_threads(mut self, n: usize) -> Self {
+        assert!(n > 0, "worker_threads must be greater than 0");
+        self.worker_threads = n;
+        self
+    }
+
+    pub fn start_paused(mut self, paused: bool) -> Self {
+        self.start_paused = paused;
+        self
+    }
+
+    pub fn enable_poll_histogram(mut self, enable: bool) -> Self {
+        self.poll_histogram = enable;
+        self
+    }
+
+    pub fn slow_poll_threshold(mut self, threshold: time::Duration) -> Self {
+        self.slow_poll_threshold = Some(threshold);
+        self
+    }
+
+    pub fn watchdog(mut self, limit: time::Duration) -> Self {
+        self.watchdog = Some(limit);
+        self
+    }
+
+    pub fn build(self) -> Result<Runtime> {
+        if self.handle_signal {
+            signal::signal_handler();
+        }
+
+        // 慢poll的调用栈同样由看门狗线程在poll执行期间请求采集
+        let watchdog = (self.watchdog.is_some() || self.slow_poll_threshold.is_some())
+            .then(|| Watchdog::new(self.watchdog, self.slow_poll_threshold))
+            .transpose()?;
+        let clock = Clock::new(self.start_paused);
+        let poller = Poller::new(self.event_capacity, self.handle_signal, &clock)?;
+        Ok(Runtime(Rc::new(_Runtime {
+            _total_tasks: UPSafeCell::new(HashMap::new()),
+            _polling: RefCell::new(None),
+            _budget: Cell::new(None),
+            _ready_wakers: UPSafeCell::new(VecDeque::new()),
+            _remote: poller.remote(),
+            _poller: UPSafeCell::new(poller),
+            _clock: clock,
+            _metrics: UPSafeCell::new(MetricsRecorder::new(self.poll_histogram)),
+            _watchdog: watchdog,
+            slow_poll_threshold: self.slow_poll_threshold,
+            event_interval: self.event_interval,
+            _finish_cb: UPSafeCell::new(Vec::new()),
+            _stop_waiters: WakerSet::default(),
+            handle_signal: self.handle_signal,
+            grace_period: Cell::new(self.grace_period),
+            signal_seen: Cell::new(signal_count()),
+            dump_seen: Cell::new(dump_count()),
+            stopped: Cell::new(false),
+        })))
+    }
+}
+
+struct _Runtime {
+    // 尚在运行中的任务及其调试信息
+    _total_tasks: UPSafeCell<HashMap<TaskId, TaskInfo>>,
+
+    // 正在被poll的任务
+    _polling: RefCell<Option<TaskAttr>>,
+
+    // 正在被poll的任务剩余的协作式调度预算
+    _budget: Cell<Option<usize>>,
+
+    // 等待被唤醒的Waker
+    _ready_wakers: UPSafeCell<VecDeque<Waker>>,
+
+    // 其它线程投递Waker的入口
+    _remote: RemoteHandle,
+
+    _poller: UPSafeCell<Poller>,
+
+    _clock: Clock,
+
+    _metrics: UPSafeCell<MetricsRecorder>,
+
+    _watchdog: Option<Watchdog>,
+
+    slow_poll_threshold: Option<time::Duration>,
+
+    event_interval: usize,
+
+    // 运行时结束时的善后处理
+    _finish_cb: UPSafeCell<Vec<FinishCb>>,
+
+    // 等待停止信号的Waker
+    _stop_waiters: WakerSet,
+
+    handle_signal: bool,
+
+    grace_period: Cell<time::Duration>,
+
+    // 已处理过的信号计数，用于判断是否有新的停止信号
+    signal_seen: Cell<usize>,
+
+    // 已处理过的任务转储信号计数
+    dump_seen: Cell<usize>,
+
+    stopped: Cell<bool>,
+}
+
+/*
+ * 运行时实例，clone后指向同一个运行时
+ * spawn、add_timer、register等函数作用于当前线程正在驱动的运行时
+ */
+#[derive(Clone)]
+pub struct Runtime(Rc<_Runtime>);
+
+impl Runtime {
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    #[inline]
+    fn total_tasks(&self) -> RefMut<'_, HashMap<TaskId, TaskInfo>> {
+        self.0._total_tasks.exclusive_access()
+    }
+
+    #[inline]
+    fn ready_wakers(&self) -> RefMut<'_, VecDeque<Waker>> {
+        self.0._ready_wakers.exclusive_access()
+    }
+
+    #[inline]
+    fn poller(&self) -> RefMut<'_, Poller> {
+        self.0._poller.exclusive_access()
+    }
+
+    #[inline]
+    fn metrics_recorder(&self) -> RefMut<'_, MetricsRecorder> {
+        self.0._metrics.exclusive_access()
+    }
+
+    fn finish_cb(&self) -> RefMut<'_, Vec<FinishCb>> {
+        self.0._finish_cb.exclusive_access()
+    }
+
+    // 将当前运行时设置为当前线程的运行时，guard释放时恢复
+    fn enter(&self) -> EnterGuard {
+        let prev = CURRENT.with(|cur| cur.borrow_mut().replace(self.clone()));
+        EnterGuard { prev }
+    }
+
+    // 提交一个任务到当前运行时
+    #[track_caller]
+    pub fn spawn<F: Future>(&self, f: F) -> JoinHandle<F::Output> {
+        let _guard = self.enter();
+        spawn(f)
+    }
+
+    // 驱动运行时直到f完成，并返回f的输出
+    // 运行时在f完成前被停止（停止信号、强制停止）时返回ErrorType::Cancelled
+    pub fn block_on<F: Future>(&self, f: F) -> Result<F::Output> {
+        let _guard = self.enter();
+        let mut handle = spawn(f);
+        self.drive(|| handle.is_finished());
+        self.finish();
+
+        match handle.take_output() {
+            Some(output) => output,
+            None => Err(ErrorType::Cancelled.into()),
+        }
+    }
+
+    // 运行直到无运行中的任务时候
+    pub fn run(&self) {
+        let _guard = self.enter();
+        self.drive(|| false);
+        self.finish();
+    }
+
+    // 运行时统计数据的快照
+    pub fn metrics(&self) -> RuntimeMetrics {
+        let poller = self.poller();
+        let live = LiveCounts {
+            tasks: self.total_tasks().len(),
+            ready_wakers: self.ready_wakers().len(),
+            io_sources: poller.sources(),
+            timers: poller.timers(),
+            loop_stalls: self.0._watchdog.as_ref().map_or(0, Watchdog::stalls),
+        };
+        drop(poller);
+        self.metrics_recorder().snapshot(live)
+    }
+
+    fn drive(&self, done: impl Fn() -> bool) {
+        loop {
+            self.check_signal();
+            run_ready_wakers();
+
+            // 判断是否还有多余的任务
+            if done() || can_finish() {
+                break;
+            }
+
+            wait();
+        }
+    }
+
+    fn finish(&self) {
+        for cb in take_finish_cbs() {
+            cb();
+        }
+
+        log::debug!("runtime done");
+    }
+
+    // 运行中的任务的快照，按任务id排序
+    pub fn dump_tasks(&self) -> Vec<TaskDump> {
+        let polling = self.0._polling.borrow().clone();
+        let polling = polling.as_ref().map(TaskAttr::get_tid);
+        let mut dumps: Vec<_> = self
+            .total_tasks()
+            .iter()
+            .map(|(tid, info)| info.dump(tid.value(), polling == Some(tid)))
+            .collect();
+        dumps.sort_by_key(TaskDump::id);
+        dumps
+    }
+
+    // 收到新的停止信号时执行停止动作，收到新的转储信号时打印全部任务
+    fn check_signal(&self) {
+        if !self.0.handle_signal {
+            return;
+        }
+
+        let count = dump_count();
+        if count != self.0.dump_seen.replace(count) {
+            let dumps = self.dump_tasks();
+            log::warn!("dump {} running tasks", dumps.len());
+            for dump in dumps {
+                log::warn!("{}", dump);
+            }
+        }
+
+        let count = signal_count();
+        if count != self.0.signal_seen.replace(count) && !self.0.stopped.replace(true) {
+            signal::stop_action(&self.0._stop_waiters, self.0.grace_period.get());
+        }
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        // 仅在最后一个实例释放时清理，且线程局部变量已销毁时无法再进入运行时
+        if Rc::strong_count(&self.0) > 1 || CURRENT.try_with(|_| ()).is_err() {
+            return;
+        }
+
+        // 在当前运行时的上下文中释放剩余的任务，释放过程中产生的Waker也会回到当前运行时
+        let _guard = self.enter();
+        loop {
+            let wakers = std::mem::take(&mut *self.ready_wakers());
+            let remote_wakers = self.0._remote.take_wakers();
+            let timers = self.poller().take_timers();
+            if wakers.is_empty() && remote_wakers.is_empty() && timers.is_empty() {
+                break;
+            }
+        }
+    }
+}
+
+struct EnterGuard {
+    prev: Option<Runtime>,
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        let cur = CURRENT.with(|cur| std::mem::replace(&mut *cur.borrow_mut(), prev));
+        // 需要在CURRENT的借用结束后再释放
+        drop(cur);
+    }
+}
+
+// 获取当前线程的运行时
+pub(crate) fn current() -> Runtime {
+    CURRENT.with(|cur| {
+        cur.borrow_mut()
+            .get_or_insert_with(|| {
+                Runtime::builder()
+                    .build()
+                    .expect("init default Runtime failed")
+            })
+            .clone()
+    })
+}
+
+// 线程退出时运行时已被销毁，此时释放的资源（如锁的guard、Stream）无需再与运行时交互
+fn try_current() -> Option<Runtime> {
+    CURRENT
+        .try_with(|cur| cur.try_borrow().ok().and_then(|cur| cur.clone()))
+        .ok()
+        .flatten()
+}
+
+struct RuntimeTaskClear {
+    tid: TaskId,
+    rt: Weak<_Runtime>,
+}
+
+impl TTaskClear for RuntimeTaskClear {
+    fn clear(&self) {
+        if let Some(rt) = self.rt.upgrade() {
+            rt._total_tasks.exclusive_access().remove(&self.tid);
+            rt._metrics.exclusive_access().remove_task(&self.tid);
+        }
+    }
+}
+
+// 提交一个任务。类似于golang语言中的go语法
+// 返回的JoinHandle可用于等待任务的输出
+#[track_caller]
+pub fn spawn<F: Future>(f: F) -> JoinHandle<F::Output> {
+    spawn_at(f, None, Location::caller())
+}
+
+// 提交一个命名的任务，名称会出现在dump_tasks的输出中
+#[track_caller]
+pub fn spawn_named<F: Future>(name: impl Into<String>, f: F) -> JoinHandle<F::Output> {
+    spawn_at(f, Some(name.into()), Location::caller())
+}
+
+// 带配置的任务提交
+#[derive(Default)]
+pub struct TaskBuilder {
+    name: Option<String>,
+}
+
+impl TaskBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    #[track_caller]
+    pub fn spawn<F: Future>(self, f: F) -> JoinHandle<F::Output> {
+        spawn_at(f, self.name, Location::caller())
+    }
+}
+
+pub(crate) fn spawn_at<F: Future>(
+    f: F,
+    mut name: Option<String>,
+    location: &'static Location<'static>,
+) -> JoinHandle<F::Output> {
+    let rt = current();
+    let (waker, handle) = Task::new_waker(f, |tid| {
+        rt.total_tasks()
+            .insert(tid.clone(), TaskInfo::new(name.take(), location));
+        RuntimeTaskClear {
+            tid,
+            rt: Rc::downgrade(&rt.0),
+        }
+    });
+
+    rt.ready_wakers().push_back(waker);
+    handle
+}
+
+// 是否还存在运行中的任务。用于runtime的退出时判断
+pub(crate) fn can_finish() -> bool {
+    let total_tasks_count =
+        variable_log!(trace @ current().total_tasks().len(), "running tasks count");
+    // 通过中断停止时，还会有一个最大等待时长的协程在执行
+    if stopped() {
+        total_tasks_count <= 1
+    } else {
+        total_tasks_count == 0
+    }
+}
+
+pub(crate) fn get_waker() -> Option<Waker> {
+    current().ready_wakers().pop_front()
+}
+
+// 执行就绪的Waker（包括执行过程中新就绪的），作为事件循环的一轮并统计其耗时
+// 每轮最多执行event_interval个，避免不断重新就绪的任务使io与定时器得不到处理
+pub(crate) fn run_ready_wakers() {
+    let rt = current();
+    let start = time::Instant::now();
+    if let Some(watchdog) = rt.0._watchdog.as_ref() {
+        watchdog.turn_start();
+    }
+    for _ in 0..rt.0.event_interval {
+        let Some(waker) = get_waker() else {
+            break;
+        };
+        waker.wake();
+    }
+    if let Some(watchdog) = rt.0._watchdog.as_ref() {
+        watchdog.turn_end();
+    }
+    rt.metrics_recorder().record_loop(start.elapsed());
+}
+
+// 外层正在poll的任务及其剩余的预算，在嵌套驱动同一个运行时（如任务中的block_on）结束后恢复
+#[derive(Default)]
+pub(crate) struct PrevPoll {
+    polling: Option<TaskAttr>,
+    budget: Option<usize>,
+    watchdog: (u64, u64),
+}
+
+// 任务开始poll，返回之前正在poll的任务及其预算
+pub(crate) fn poll_start(attr: &TaskAttr) -> PrevPoll {
+    let Some(rt) = try_current() else {
+        return PrevPoll::default();
+    };
+    if let Some(info) = rt.total_tasks().get_mut(attr.get_tid()) {
+        info.clear_waiting();
+    }
+    PrevPoll {
+        polling: rt.0._polling.replace(Some(attr.clone())),
+        budget: rt.0._budget.replace(Some(coop::BUDGET)),
+        watchdog: rt.0._watchdog.as_ref().map_or((0, 0), Watchdog::poll_start),
+    }
+}
+
+// 任务结束poll，记录单次poll的耗时
+pub(crate) fn poll_end(attr: &TaskAttr, prev: PrevPoll, dur: time::Duration) {
+    let Some(rt) = try_current() else {
+        return;
+    };
+    let tid = attr.get_tid();
+    rt.0._budget.set(prev.budget);
+    rt.0._polling.replace(prev.polling);
+    rt.metrics_recorder().record_poll(tid, dur);
+    let backtrace =
+        rt.0._watchdog
+            .as_ref()
+            .and_then(|watchdog| watchdog.poll_end(prev.watchdog));
+
+    if let Some(threshold) = rt.0.slow_poll_threshold
+        && dur > threshold
+    {
+        rt.metrics_recorder().record_slow_poll();
+        // 任务可能已在poll中结束并被移除
+        let task = match rt.total_tasks().get(tid) {
+            Some(info) => info.dump(tid.value(), false).describe(),
+            None => tid.to_string(),
+        };
+        // 调用栈由看门狗线程在poll超过阈值时请求采集，poll刚超过阈值就结束时可能来不及采集
+        match backtrace {
+            Some(mut backtrace) => {
+                backtrace.resolve();
+                log::warn!(
+                    "slow poll: {} took {:?} (threshold {:?}), backtrace:\n{:?}",
+                    task,
+                    dur,
+                    threshold,
+                    backtrace
+                );
+            }
+            None => log::warn!(
+                "slow poll: {} took {:?} (threshold {:?}), backtrace not captured",
+                task,
+                dur,
+                threshold
+            ),
+        }
+    }
+}
+
+// 记录正在poll的任务所等待的对象，用于dump_tasks
+pub(crate) fn wait_on(reason: WaitOn) {
+    let Some(rt) = try_current() else {
+        return;
+    };
+    let polling = rt.0._polling.borrow();
+    if let Some(attr) = polling.as_ref()
+        && let Some(info) = rt.total_tasks().get_mut(attr.get_tid())
+    {
+        info.wait_on(reason);
+    }
+}
+
+// 以正在poll的任务的属性调用f，不在任务的poll中时返回None
+// 可能在运行时内部打印日志时被调用，因此借用失败时同样返回None
+pub(crate) fn with_polling<R>(f: impl FnOnce(&TaskAttr) -> R) -> Option<R> {
+    let rt = try_current()?;
+    let polling = rt.0._polling.try_borrow().ok()?;
+    polling.as_ref().map(f)
+}
+
+// 当前运行时中运行中的任务的快照
+pub fn dump_tasks() -> Vec<TaskDump> {
+    current().dump_tasks()
+}
+
+// 当前运行时统计数据的快照
+pub fn metrics() -> RuntimeMetrics {
+    current().metrics()
+}
+
+// 新增任务
+pub(crate) fn add_waker(waker: Waker) {
+    if let Some(rt) = try_current() {
+        rt.ready_wakers().push_back(waker);
+    }
+}
+
+// 等待可执行任务（事件就绪），仍有就绪的Waker时只获取已就绪的事件
+pub(crate) fn wait() {
+    let block = current().ready_wakers().is_empty();
+    poll_wakers(block);
+}
+
+fn poll_wakers(block: bool) {
+    let rt = current();
+    let wakers = rt.poller().poll(block, &rt.0._clock);
+    let mut ready_wakers = rt.ready_wakers();
+    ready_wakers.extend(wakers);
+    ready_wakers.extend(rt.0._remote.take_wakers());
+}
+
+pub(crate) fn remote() -> RemoteHandle {
+    current().0._remote.clone()
+}
+
+pub(crate) fn try_remote() -> Option<RemoteHandle> {
+    try_current().map(|rt| rt.0._remote.clone())
+}
+
+// 当前运行时的时间，线程上尚无运行时（或已销毁）时使用系统时间
+pub(crate) fn now() -> time::Instant {
+    try_current().map_or_else(time::Instant::now, |rt| rt.0._clock.now())
+}
+
+pub(crate) fn clock<R>(f: impl FnOnce(&Clock) -> R) -> R {
+    f(&current().0._clock)
+}
+
+// 推进暂停的时钟，并将到期的定时器加入就绪队列
+pub(crate) fn advance_clock(dur: time::Duration) {
+    let rt = current();
+    rt.0._clock.advance(dur);
+    let wakers = rt.poller().poll_timers(rt.0._clock.now());
+    rt.ready_wakers().extend(wakers);
+}
+
+// 添加一个定时任务
+pub(crate) fn add_timer(wake_at: time::Instant, waker: Waker) -> TimerDropper {
+    current().poller().add_timer(wake_at, waker)
+}
+
+pub(crate) fn register<S: mio::event::Source>(
+    events: Vec<crate::io_event::Event>,
+    io_event: &IoEvent,
+    source: &mut S,
+) -> Result<()> {
+    current().poller().register(events, io_event, source)
+}
+
+pub(crate) fn reregister<S: mio::event::Source>(
+    events: Vec<crate::io_event::Event>,
+    io_event: &IoEvent,
+    source: &mut S,
+) -> Result<()> {
+    current().poller().reregister(events, io_event, source)
+}
+
+pub(crate) fn deregister<S: mio::event::Source>(io_event: &IoEvent, source: &mut S) -> Result<()> {
+    match try_current() {
+        Some(rt) => rt.poller().deregister(io_event, source),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn force_stop() {
+    current().total_tasks().clear();
+}
+
+pub(crate) fn register_rt_finish_cb(cb: FinishCb) {
+    current().finish_cb().push(cb);
+}
+
+pub(crate) fn take_finish_cbs() -> Vec<FinishCb> {
+    std::mem::take(&mut current().finish_cb())
+}
+
+pub(crate) fn stop_waiters() -> WakerSet {
+    current().0._stop_waiters.clone()
+}
+
+pub(crate) fn rt_stopped() -> bool {
+    current().0.stopped.get()
+}
+
+pub(crate) fn set_grace_period(dur: time::Duration) {
+    current().0.grace_period.set(dur);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, time};
+
+    use crate::{
+        block_on, run,
+        runtime::{Runtime, force_stop, spawn},
+        sleep,
+    };
+
+    #[test]
+    fn test_isolated_runtimes() {
+        let rt1 = Runtime::builder().handle_signal(false).build().unwrap();
+        let rt2 = Runtime::builder()
+            .event_capacity(16)
+            .handle_signal(false)
+            .build()
+            .unwrap();
+
+        let output = rt1.block_on(async {
+            // 嵌套运行另一个运行时，任务之间互不影响
+            let inner = rt2.block_on(async {
+                sleep(time::Duration::from_millis(10)).await;
+                1
+            });
+            inner.unwrap() + 1
+        });
+        assert_eq!(output.unwrap(), 2);
+
+        // 运行时可以被多次驱动
+        assert_eq!(
+            rt2.block_on(async { spawn(async { 3 }).await.unwrap() })
+                .unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_drop_pending_tasks() {
+        let counter = Rc::new(());
+        {
+            let rt = Runtime::builder().handle_signal(false).build().unwrap();
+            let c = counter.clone();
+            rt.block_on(async move {
+                spawn(async move {
+                    let _c = c;
+                    sleep(time::Duration::from_secs(10)).await;
+                });
+            })
+            .unwrap();
+            assert_eq!(Rc::strong_count(&counter), 2);
+        }
+        // 运行时释放后，等待中的任务也随之释放
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn test_block_on() {
+        let flag = Rc::new(());
+        let f = flag.clone();
+        let output = block_on(async move {
+            spawn(async move {
+                let _f = f;
+                sleep(time::Duration::from_millis(50)).await;
+            });
+            "main"
+        });
+        // 主future完成即返回，其余任务仍保留在运行时中
+        assert_eq!(output.unwrap(), "main");
+        assert_eq!(Rc::strong_count(&flag), 2);
+
+        run();
+        assert_eq!(Rc::strong_count(&flag), 1);
+    }
+
+    #[test]
+    fn test_block_on_stopped() {
+        let rt = Runtime::builder().handle_signal(false).build().unwrap();
+        let output = rt.block_on(async {
+            spawn(async {
+                sleep(time::Duration::from_millis(10)).await;
+                force_stop();
+            });
+            sleep(time::Duration::from_secs(10)).await;
+            "main"
+        });
+        // 主future未完成时运行时被停止，返回错误而不是panic
+        assert!(output.is_err_and(|e| e.is_cancelled()));
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, Waker},
+    time,
+};
+
+use crate::result::Result;
+
+// 打断阻塞中的poll的token，io的token从0开始递增，不会与此值冲突
+const WAKE_TOKEN: mio::Token = mio::Token(usize::MAX);
+
+pub(super) const READABLE: usize = 0b01;
+pub(super) const WRITABLE: usize = 0b10;
+const READINESS_MASK: usize = READABLE | WRITABLE;
+const TICK_SHIFT: usize = 2;
+
+/*
+ * 单个io的就绪状态，由驱动事件循环的工作线程写入，等待io的任务可能在任意工作线程上读取
+ * 低两位为就绪状态，其余位为事件计数：任务遇到WouldBlock后清除就绪状态时，据此判断期间是否有新的事件到达
+ */
+pub(super) struct ScheduledIo {
+    readiness: AtomicUsize,
+    reader: Mutex<Option<Waker>>,
+    writer: Mutex<Option<Waker>>,
+}
+
+impl ScheduledIo {
+    fn new() -> Self {
+        // 注册前可能已经就绪，先尝试一次读写，遇到WouldBlock后再等待事件
+        Self {
+            readiness: AtomicUsize::new(READINESS_MASK),
+            reader: Mutex::new(None),
+            writer: Mutex::new(None),
+        }
+    }
+
+    fn set_readiness(&self, ready: usize) {
+        let _ = self
+            .readiness
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |cur| {
+                let tick = (cur >> TICK_SHIFT).wrapping_add(1);
+                Some((tick << TICK_SHIFT) | (cur & READINESS_MASK) | ready)
+            });
+
+        for (interest, slot) in [(READABLE, &self.reader), (WRITABLE, &self.writer)] {
+            if ready & interest != 0
+                && let Some(waker) = slot.lock().unwrap().take()
+            {
+                waker.wake();
+            }
+        }
+    }
+
+    // 就绪时返回当前的状态，用于之后清除就绪状态
+    pub(super) fn poll_ready(&self, cx: &mut Context<'_>, interest: usize) -> Poll<usize> {
+        let cur = self.readiness.load(Ordering::Acquire);
+        if cur & interest != 0 {
+            return Poll::Ready(cur);
+        }
+
+        let slot = match interest {
+            READABLE => &self.reader,
+            _ => &self.writer,
+        };
+        {
+            let mut waker = slot.lock().unwrap();
+            if !waker.as_ref().is_some_and(|w| w.will_wake(cx.waker())) {
+                waker.replace(cx.waker().clone());
+            }
+        }
+
+        // 保存Waker期间可能有新的事件到达
+        let cur = self.readiness.load(Ordering::Acquire);
+        if cur & interest != 0 {
+            Poll::Ready(cur)
+        } else {
+            Poll::Pending
+        }
+    }
+
+    // 只有在读取状态之后没有新的事件时才清除
+    pub(super) fn clear_readiness(&self, snapshot: usize, interest: usize) {
+        let _ = self
+            .readiness
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |cur| {
+                (cur >> TICK_SHIFT == snapshot >> TICK_SHIFT).then_some(cur & !interest)
+            });
+    }
+}
+
+struct Poller {
+    poll: mio::Poll,
+    events: mio::Events,
+}
+
+/*
+ * 全部工作线程共享的io与定时器驱动
+ * 同一时刻只有一个空闲的工作线程持有mio::Poll并阻塞在其中，其它空闲的工作线程休眠等待任务
+ * 就绪的io与到期的定时器唤醒对应的任务，任务进入驱动线程的队列，再被其它工作线程窃取
+ */
+pub(super) struct Driver {
+    poller: Mutex<Poller>,
+    registry: mio::Registry,
+    waker: mio::Waker,
+    // 是否有工作线程阻塞在poll中
+    blocked: AtomicBool,
+    ios: Mutex<HashMap<mio::Token, Arc<ScheduledIo>>>,
+    next_token: AtomicUsize,
+    // 按到期时间排序的定时器，相同到期时间按创建顺序
+    timers: Mutex<BTreeMap<(time::Instant, u64), Waker>>,
+    next_timer: AtomicU64,
+}
+
+impl Driver {
+    pub(super) fn new(capacity: usize) -> Result<Self> {
+        let poll = mio::Poll::new()?;
+        let registry = poll.registry().try_clone()?;
+        let waker = mio::Waker::new(&registry, WAKE_TOKEN)?;
+        Ok(Self {
+            poller: Mutex::new(Poller {
+                poll,
+                events: mio::Events::with_capacity(capacity),
+            }),
+            registry,
+            waker,
+            blocked: AtomicBool::new(false),
+            ios: Mutex::new(HashMap::new()),
+            next_token: AtomicUsize::new(0),
+            timers: Mutex::new(BTreeMap::new()),
+            next_timer: AtomicU64::new(0),
+        })
+    }
+
+    // 打断阻塞中的poll，使驱动线程重新检查运行队列与定时器
+    pub(super) fn unpark(&self) {
+        if self.blocked.load(Ordering::SeqCst)
+            && let Err(e) = self.waker.wake()
+        {
+            log::warn!("driver unpark failed: {}", e);
+        }
+    }
+
+    pub(super) fn try_lock(&self) -> Option<DriverGuard<'_>> {
+        self.poller.try_lock().ok().map(|poller| DriverGuard {
+            driver: self,
+            poller,
+        })
+    }
+
+    pub(super) fn register(
+        &self,
+        source: &mut impl mio::event::Source,
+    ) -> Result<(mio::Token, Arc<ScheduledIo>)> {
+        let token = mio::Token(self.next_token.fetch_add(1, Ordering::Relaxed));
+        let io = Arc::new(ScheduledIo::new());
+        self.ios.lock().unwrap().insert(token, io.clone());
+        if let Err(e) = self.registry.register(
+            source,
+            token,
+            mio::Interest::READABLE | mio::Interest::WRITABLE,
+        ) {
+            self.ios.lock().unwrap().remove(&token);
+            return Err(e.into());
+        }
+        Ok((token, io))
+    }
+
+    pub(super) fn deregister(&self, token: mio::Token, source: &mut impl mio::event::Source) {
+        if let Err(e) = self.registry.deregister(source) {
+            log::warn!("deregister {:?} failed: {}", token, e);
+        }
+        self.ios.lock().unwrap().remove(&token);
+    }
+
+    pub(super) fn add_timer(&self, deadline: time::Instant, waker: Waker) -> u64 {
+        let id = self.next_timer.fetch_add(1, Ordering::Relaxed);
+        let earliest = {
+            let mut timers = self.timers.lock().unwrap();
+            timers.insert((deadline, id), waker);
+            timers.keys().next() == Some(&(deadline, id))
+        };
+        // 比驱动线程当前的等待时长更早到期
+        if earliest {
+            self.unpark();
+        }
+        id
+    }
+
+    pub(super) fn update_timer(&self, deadline: time::Instant, id: u64, waker: &Waker) {
+        if let Some(old) = self.timers.lock().unwrap().get_mut(&(deadline, id))
+            && !old.will_wake(waker)
+        {
+            *old = waker.clone();
+        }
+    }
+
+    pub(super) fn remove_timer(&self, deadline: time::Instant, id: u64) {
+        self.timers.lock().unwrap().remove(&(deadline, id));
+    }
+
+    fn next_deadline(&self) -> Option<time::Instant> {
+        self.timers.lock().unwrap().keys().next().map(|(at, _)| *at)
+    }
+
+    fn fire_timers(&self) {
+        let expired = {
+            let mut timers = self.timers.lock().unwrap();
+            let later = timers.split_off(&(time::Instant::now(), u64::MAX));
+            std::mem::replace(&mut *timers, later)
+        };
+        for (_, waker) in expired {
+            waker.wake();
+        }
+    }
+
+    // 运行时停止时释放全部Waker，解除任务与驱动之间的引用
+    pub(super) fn clear(&self) {
+        let timers = std::mem::take(&mut *self.timers.lock().unwrap());
+        drop(timers);
+        let ios = std::mem::take(&mut *self.ios.lock().unwrap());
+        for io in ios.values() {
+            io.reader.lock().unwrap().take();
+            io.writer.lock().unwrap().take();
+        }
+    }
+}
+
+pub(super) struct DriverGuard<'a> {
+    driver: &'a Driver,
+    poller: MutexGuard<'a, Poller>,
+}
+
+impl DriverGuard<'_> {
+    // 标记为阻塞后再检查是否有任务（或运行时已停止），避免错过投递任务时的unpark
+    pub(super) fn park(&mut self, wakeup: impl Fn() -> bool) {
+        self.driver.blocked.store(true, Ordering::SeqCst);
+        let block = !wakeup();
+        self.turn(block);
+        self.driver.blocked.store(false, Ordering::SeqCst);
+    }
+
+    // 获取已就绪的事件，block为true时等待到有事件或最近的定时器到期
+    pub(super) fn turn(&mut self, block: bool) {
+        let timeout = match block {
+            true => self
+                .driver
+                .next_deadline()
+                .map(|at| at.saturating_duration_since(time::Instant::now())),
+            false => Some(time::Duration::ZERO),
+        };
+
+        let Poller { poll, events } = &mut *self.poller;
+        if let Err(e) = poll.poll(events, timeout)
+            && e.kind() != io::ErrorKind::Interrupted
+        {
+            log::error!("driver poll failed: {}", e);
+        }
+
+        for event in events.iter() {
+            if event.token() == WAKE_TOKEN {
+                continue;
+            }
+
+            let mut ready = 0;
+            if event.is_readable() || event.is_read_closed() {
+                ready |= READABLE;
+            }
+            if event.is_writable() || event.is_write_closed() {
+                ready |= WRITABLE;
+            }
+            if event.is_error() {
+                ready |= READINESS_MASK;
+            }
+            let io = self.driver.ios.lock().unwrap().get(&event.token()).cloned();
+            if let Some(io) = io {
+                io.set_readiness(ready);
+            }
+        }
+
+        self.driver.fire_timers();
+    }
+}
@@ -0,0 +1,549 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    panic::Location,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use crate::{
+    result::{ErrorType, Result},
+    runtime::{Builder, remote::remote_join},
+};
+
+mod driver;
+pub mod net;
+mod task;
+pub mod time;
+
+use driver::{Driver, DriverGuard};
+use task::Task;
+
+pub use time::{sleep, sleep_until};
+
+/// 多线程运行时中任务的句柄，可在任意线程或运行时中await
+pub use crate::runtime::remote::RemoteJoinHandle as JoinHandle;
+
+thread_local! {
+    // 当前线程所属的工作线程组及其编号
+    static WORKER: RefCell<Option<(Arc<Shared>, usize)>> = const { RefCell::new(None) };
+}
+
+// 当前工作线程所属的运行时，任务中的io与定时器通过它注册到共享驱动
+fn context() -> Arc<Shared> {
+    WORKER
+        .with(|w| w.borrow().as_ref().map(|(shared, _)| shared.clone()))
+        .expect("must be called inside a multi-thread runtime")
+}
+
+/*
+ * 工作线程之间共享的调度状态
+ * 每个工作线程有自己的运行队列，被唤醒的任务进入唤醒方所在工作线程的队列，非工作线程提交或唤醒的任务进入全局队列
+ * 空闲的工作线程从其它队列的尾部窃取任务，已经开始执行的任务同样可以被窃取，因此阻塞在某个工作线程上的任务不会拖住其它任务
+ */
+struct Shared {
+    injector: Mutex<VecDeque<Arc<Task>>>,
+    queues: Vec<Mutex<VecDeque<Arc<Task>>>>,
+    // 尚未结束的任务，运行时停止时释放其future
+    tasks: Mutex<HashMap<u64, Arc<Task>>>,
+    next_task: AtomicU64,
+    driver: Driver,
+    idle: Mutex<Idle>,
+    condvar: Condvar,
+    // 每执行该数量的任务检查一次全局队列与驱动，避免本地队列中的任务使其饿死
+    event_interval: usize,
+    shutdown: AtomicBool,
+}
+
+impl Shared {
+    #[track_caller]
+    fn spawn<F>(self: &Arc<Self>, f: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (completion, handle) = remote_join();
+        let id = self.next_task.fetch_add(1, Ordering::Relaxed);
+        let task = Task::new(
+            id,
+            Box::pin(async move {
+                completion.complete(Ok(f.await));
+            }),
+            self,
+            Location::caller(),
+        );
+        self.tasks.lock().unwrap().insert(id, task.clone());
+        self.schedule(task);
+        handle
+    }
+
+    fn schedule(&self, task: Arc<Task>) {
+        if self.is_shutdown() {
+            return;
+        }
+
+        let local = WORKER.with(|w| match w.borrow().as_ref() {
+            Some((shared, index)) if std::ptr::eq(Arc::as_ptr(shared), self) => Some(*index),
+            _ => None,
+        });
+        match local {
+            Some(index) => self.queues[index].lock().unwrap().push_back(task),
+            None => self.injector.lock().unwrap().push_back(task),
+        }
+        self.notify();
+    }
+
+    // 唤醒一个休眠的工作线程，没有休眠的工作线程时打断阻塞在驱动中的工作线程
+    fn notify(&self) {
+        if !self.wake_sleeper() {
+            self.driver.unpark();
+        }
+    }
+
+    // 每次唤醒一个不同的工作线程，已被唤醒但还未醒来的工作线程不会重复计入
+    fn wake_sleeper(&self) -> bool {
+        let mut idle = self.idle.lock().unwrap();
+        if idle.sleepers > idle.permits {
+            idle.permits += 1;
+            self.condvar.notify_one();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn remove_task(&self, id: u64) {
+        self.tasks.lock().unwrap().remove(&id);
+    }
+
+    fn has_tasks(&self) -> bool {
+        !self.injector.lock().unwrap().is_empty()
+            || self.queues.iter().any(|q| !q.lock().unwrap().is_empty())
+    }
+
+    fn next_task(&self, index: usize, tick: usize) -> Option<Arc<Task>> {
+        // 定期优先检查全局队列
+        if tick.is_multiple_of(self.event_interval)
+            && let Some(task) = self.injector.lock().unwrap().pop_front()
+        {
+            return Some(task);
+        }
+        if let Some(task) = self.queues[index].lock().unwrap().pop_front() {
+            return Some(task);
+        }
+        if let Some(task) = self.injector.lock().unwrap().pop_front() {
+            return Some(task);
+        }
+        self.steal(index)
+    }
+
+    // 从其它工作线程的队列尾部窃取一半的任务
+    fn steal(&self, index: usize) -> Option<Arc<Task>> {
+        let n = self.queues.len();
+        for victim in (1..n).map(|i| (index + i) % n) {
+            let mut stolen = {
+                let mut queue = self.queues[victim].lock().unwrap();
+                let len = queue.len();
+                queue.split_off(len / 2)
+            };
+
+            if let Some(task) = stolen.pop_front() {
+                self.queues[index].lock().unwrap().extend(stolen);
+                return Some(task);
+            }
+        }
+        None
+    }
+
+    fn try_drive(&self) -> Option<DriverGuard<'_>> {
+        let driver = self.driver.try_lock()?;
+        self.idle.lock().unwrap().driving = true;
+        Some(driver)
+    }
+
+    // 离开驱动去执行任务，由一个休眠的工作线程接替驱动
+    fn release_driver(&self, driver: DriverGuard<'_>) {
+        drop(driver);
+        let mut idle = self.idle.lock().unwrap();
+        idle.driving = false;
+        if idle.sleepers > idle.permits {
+            idle.permits += 1;
+            self.condvar.notify_one();
+        }
+    }
+
+    // 没有任务且有其它工作线程在驱动时休眠，直到被唤醒或运行时停止
+    fn sleep(&self) {
+        let mut idle = self.idle.lock().unwrap();
+        // 持有锁时检查，投递任务或释放驱动后的唤醒在此之后才能进行
+        if self.has_tasks() || self.is_shutdown() || !idle.driving {
+            return;
+        }
+
+        idle.sleepers += 1;
+        while !self.is_shutdown() {
+            idle = self.condvar.wait(idle).unwrap();
+            if idle.permits > 0 {
+                idle.permits -= 1;
+                break;
+            }
+        }
+        idle.sleepers -= 1;
+    }
+
+    fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+}
+
+// 休眠的工作线程数量，以及已发出但还未被消耗的唤醒次数
+#[derive(Default)]
+struct Idle {
+    sleepers: usize,
+    permits: usize,
+    // 是否有工作线程持有驱动
+    driving: bool,
+}
+
+struct Worker {
+    shared: Arc<Shared>,
+    index: usize,
+}
+
+impl Worker {
+    fn run(&self) {
+        WORKER.with(|w| w.replace(Some((self.shared.clone(), self.index))));
+
+        let mut tick = 0usize;
+        while !self.shared.is_shutdown() {
+            tick = tick.wrapping_add(1);
+            if let Some(task) = self.shared.next_task(self.index, tick) {
+                task.run(&self.shared);
+                // 持续有任务时也定期获取已就绪的事件，其它工作线程正在驱动时跳过
+                if tick.is_multiple_of(self.shared.event_interval)
+                    && let Some(mut driver) = self.shared.try_drive()
+                {
+                    driver.turn(false);
+                    self.shared.release_driver(driver);
+                }
+                continue;
+            }
+
+            // 没有任务时由其中一个工作线程阻塞在驱动中，其余的休眠
+            match self.shared.try_drive() {
+                Some(mut driver) => {
+                    driver.park(|| self.shared.has_tasks() || self.shared.is_shutdown());
+                    self.shared.release_driver(driver);
+                }
+                None => self.shared.sleep(),
+            }
+        }
+
+        WORKER.with(|w| w.take());
+    }
+}
+
+impl Builder {
+    // 创建多线程运行时，全部工作线程共享同一个io与定时器驱动
+    // 多线程模式下不响应停止信号，由MultiThreadRuntime的释放来停止
+    pub fn build_multi_thread(self) -> Result<MultiThreadRuntime> {
+        let n = self.worker_threads;
+        let shared = Arc::new(Shared {
+            injector: Mutex::new(VecDeque::new()),
+            queues: (0..n).map(|_| Mutex::new(VecDeque::new())).collect(),
+            tasks: Mutex::new(HashMap::new()),
+            next_task: AtomicU64::new(0),
+            driver: Driver::new(self.event_capacity)?,
+            idle: Mutex::new(Idle::default()),
+            condvar: Condvar::new(),
+            event_interval: self.event_interval,
+            shutdown: AtomicBool::new(false),
+        });
+
+        let mut rt = MultiThreadRuntime {
+            shared: shared.clone(),
+            threads: Vec::with_capacity(n),
+        };
+        for index in 0..n {
+            let shared = shared.clone();
+            // 创建失败时rt的释放会停止已创建的工作线程
+            let thread = thread::Builder::new()
+                .name(format!("mini-runtime-worker-{}", index))
+                .spawn(move || Worker { shared, index }.run())?;
+            rt.threads.push(thread);
+        }
+        Ok(rt)
+    }
+}
+
+/// 多线程运行时，任务需要满足Send，执行期间可在工作线程之间迁移
+pub struct MultiThreadRuntime {
+    shared: Arc<Shared>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl MultiThreadRuntime {
+    #[track_caller]
+    pub fn spawn<F>(&self, f: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.shared.spawn(f)
+    }
+
+    // 阻塞当前线程直到f完成，f在工作线程上执行；不能在工作线程中调用
+    pub fn block_on<F>(&self, f: F) -> Result<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        self.spawn(async move {
+            let _ = tx.send(f.await);
+        });
+
+        // f panic时发送端随任务释放
+        rx.recv().map_err(|_| ErrorType::Cancelled.into())
+    }
+}
+
+impl Drop for MultiThreadRuntime {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        {
+            let _idle = self.shared.idle.lock().unwrap();
+            self.shared.condvar.notify_all();
+        }
+        self.shared.driver.unpark();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+
+        // 释放未完成的任务，其句柄得到ErrorType::Cancelled；释放future时可能再唤醒其它任务，停止后不再入队
+        let tasks = std::mem::take(&mut *self.shared.tasks.lock().unwrap());
+        for task in tasks.values() {
+            task.cancel();
+        }
+        self.shared.injector.lock().unwrap().clear();
+        for queue in self.shared.queues.iter() {
+            queue.lock().unwrap().clear();
+        }
+        self.shared.driver.clear();
+    }
+}
+
+/// 在当前的多线程运行时中提交一个任务，只能在工作线程中调用
+/// 任务优先进入当前工作线程的队列，空闲的工作线程会将其窃取
+#[track_caller]
+pub fn spawn<F>(f: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    context().spawn(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Barrier, Mutex},
+        task::{Poll, Waker},
+        thread, time,
+    };
+
+    use crate::{
+        helper::poll_fn,
+        runtime::{
+            Runtime,
+            multi_thread::{
+                net::{TcpListener, TcpStream},
+                sleep, spawn,
+            },
+        },
+    };
+
+    // 可在任意线程上设置的标记，设置时唤醒等待的任务
+    #[derive(Clone, Default)]
+    struct Flag(Arc<Mutex<(bool, Option<Waker>)>>);
+
+    impl Flag {
+        fn set(&self) {
+            let waker = {
+                let mut state = self.0.lock().unwrap();
+                state.0 = true;
+                state.1.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+
+        async fn wait(&self) {
+            poll_fn(|cx| {
+                let mut state = self.0.lock().unwrap();
+                if state.0 {
+                    return Poll::Ready(());
+                }
+                state.1.replace(cx.waker().clone());
+                Poll::Pending
+            })
+            .await
+        }
+    }
+
+    #[test]
+    fn test_multi_thread() {
+        let rt = Runtime::builder()
+            .worker_threads(4)
+            .build_multi_thread()
+            .unwrap();
+
+        let results = rt
+            .block_on(async {
+                // 每个任务都要等到4个任务同时在执行才能结束，只有分布在4个工作线程上才能完成
+                let barrier = Arc::new(Barrier::new(4));
+                let handles: Vec<_> = (0..4)
+                    .map(|i| {
+                        let barrier = barrier.clone();
+                        spawn(async move {
+                            barrier.wait();
+                            (i, thread::current().id())
+                        })
+                    })
+                    .collect();
+
+                let mut results = Vec::new();
+                for handle in handles {
+                    results.push(handle.await.unwrap());
+                }
+                results
+            })
+            .unwrap();
+
+        assert_eq!(
+            results.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            (0..4).collect::<Vec<_>>()
+        );
+        let threads: HashSet<_> = results.iter().map(|(_, id)| *id).collect();
+        assert_eq!(threads.len(), 4);
+
+        // 非工作线程提交的任务
+        let handle = rt.spawn(async { thread::current().name().map(String::from) });
+        let name = rt.block_on(handle).unwrap().unwrap().unwrap();
+        assert!(name.starts_with("mini-runtime-worker-"));
+
+        // 共享驱动中的定时器
+        let elapsed = rt
+            .block_on(async {
+                let start = time::Instant::now();
+                let handle = spawn(sleep(time::Duration::from_millis(50)));
+                sleep(time::Duration::from_millis(10)).await;
+                handle.await.unwrap();
+                start.elapsed()
+            })
+            .unwrap();
+        assert!(elapsed >= time::Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_steal_started_task() {
+        let rt = Runtime::builder()
+            .worker_threads(2)
+            .build_multi_thread()
+            .unwrap();
+
+        let (woken_on, resumed_on, blocked) = rt
+            .block_on(async {
+                let flag = Flag::default();
+                let waiter = {
+                    let flag = flag.clone();
+                    spawn(async move {
+                        flag.wait().await;
+                        (thread::current().id(), time::Instant::now())
+                    })
+                };
+                // 等待waiter开始执行并挂起
+                sleep(time::Duration::from_millis(10)).await;
+
+                // 在工作线程上唤醒waiter后阻塞该线程，waiter进入其队列，只能被另一个工作线程窃取执行
+                let blocker = spawn(async move {
+                    flag.set();
+                    let start = time::Instant::now();
+                    thread::sleep(time::Duration::from_millis(200));
+                    (thread::current().id(), start)
+                });
+
+                let (resumed_on, resumed_at) = waiter.await.unwrap();
+                let (woken_on, blocked_at) = blocker.await.unwrap();
+                (woken_on, resumed_on, resumed_at - blocked_at)
+            })
+            .unwrap();
+
+        assert_ne!(woken_on, resumed_on);
+        assert!(blocked < time::Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_shared_driver() {
+        let rt = Runtime::builder()
+            .worker_threads(4)
+            .build_multi_thread()
+            .unwrap();
+
+        let echoed = rt
+            .block_on(async {
+                let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+                let addr = listener.local_addr().unwrap();
+                let server = spawn(async move {
+                    for _ in 0..8 {
+                        let (stream, _) = listener.accept().await.unwrap();
+                        // 每个连接在各自的任务中回写，任务可能在任意工作线程上被唤醒
+                        spawn(async move {
+                            let mut buf = [0u8; 64];
+                            loop {
+                                let n = stream.read(&mut buf).await.unwrap();
+                                if n == 0 {
+                                    break;
+                                }
+                                stream.write_all(&buf[..n]).await.unwrap();
+                            }
+                        });
+                    }
+                });
+
+                let clients: Vec<_> = (0..8)
+                    .map(|i| {
+                        spawn(async move {
+                            let stream = TcpStream::connect(addr).await.unwrap();
+                            let msg = format!("hello {}", i);
+                            stream.write_all(msg.as_bytes()).await.unwrap();
+                            let mut buf = [0u8; 64];
+                            let mut n = 0;
+                            while n < msg.len() {
+                                n += stream.read(&mut buf[n..]).await.unwrap();
+                            }
+                            String::from_utf8(buf[..n].to_vec()).unwrap() == msg
+                        })
+                    })
+                    .collect();
+
+                let mut echoed = 0;
+                for client in clients {
+                    if client.await.unwrap() {
+                        echoed += 1;
+                    }
+                }
+                server.await.unwrap();
+                echoed
+            })
+            .unwrap();
+
+        assert_eq!(echoed, 8);
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    io::{self, Read, Write},
+    net::SocketAddr,
+    sync::Arc,
+};
+
+use crate::{helper::poll_fn, result::Result};
+
+use super::{
+    Shared, context,
+    driver::{READABLE, ScheduledIo, WRITABLE},
+};
+
+/*
+ * 注册到多线程运行时共享驱动中的io
+ * 就绪事件唤醒的是最近一次等待该io的任务，任务迁移到其它工作线程后同样能被唤醒
+ */
+struct Registration<S: mio::event::Source> {
+    source: S,
+    token: mio::Token,
+    io: Arc<ScheduledIo>,
+    shared: Arc<Shared>,
+}
+
+impl<S: mio::event::Source> Registration<S> {
+    // 只能在多线程运行时的工作线程中调用
+    fn new(mut source: S) -> Result<Self> {
+        let shared = context();
+        let (token, io) = shared.driver.register(&mut source)?;
+        Ok(Self {
+            source,
+            token,
+            io,
+            shared,
+        })
+    }
+
+    // 执行非阻塞的io操作，遇到WouldBlock时等待io再次就绪
+    async fn io<R>(&self, interest: usize, mut f: impl FnMut(&S) -> io::Result<R>) -> Result<R> {
+        loop {
+            let snapshot = poll_fn(|cx| self.io.poll_ready(cx, interest)).await;
+            match f(&self.source) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.io.clear_readiness(snapshot, interest);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                res => return Ok(res?),
+            }
+        }
+    }
+}
+
+impl<S: mio::event::Source> Drop for Registration<S> {
+    fn drop(&mut self) {
+        self.shared.driver.deregister(self.token, &mut self.source);
+    }
+}
+
+/// 多线程运行时中的tcp监听，接受的连接可在任意工作线程上读写
+pub struct TcpListener {
+    io: Registration<mio::net::TcpListener>,
+}
+
+impl TcpListener {
+    pub fn bind(addr: SocketAddr) -> Result<Self> {
+        Ok(Self {
+            io: Registration::new(mio::net::TcpListener::bind(addr)?)?,
+        })
+    }
+
+    pub async fn accept(&self) -> Result<(TcpStream, SocketAddr)> {
+        let (stream, addr) = self.io.io(READABLE, |listener| listener.accept()).await?;
+        Ok((TcpStream::new(stream)?, addr))
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.io.source.local_addr()?)
+    }
+}
+
+/// 多线程运行时中的tcp连接
+pub struct TcpStream {
+    io: Registration<mio::net::TcpStream>,
+}
+
+impl TcpStream {
+    fn new(stream: mio::net::TcpStream) -> Result<Self> {
+        Ok(Self {
+            io: Registration::new(stream)?,
+        })
+    }
+
+    pub async fn connect(addr: SocketAddr) -> Result<Self> {
+        let stream = Self::new(mio::net::TcpStream::connect(addr)?)?;
+        // 可写时连接完成，连接失败的错误通过take_error获取
+        stream
+            .io
+            .io(WRITABLE, |stream| match stream.take_error()? {
+                Some(e) => Err(e),
+                None => match stream.peer_addr() {
+                    Ok(_) => Ok(()),
+                    Err(e) if e.kind() == io::ErrorKind::NotConnected => {
+                        Err(io::ErrorKind::WouldBlock.into())
+                    }
+                    Err(e) => Err(e),
+                },
+            })
+            .await?;
+        Ok(stream)
+    }
+
+    pub async fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.io.io(READABLE, |mut stream| stream.read(buf)).await
+    }
+
+    pub async fn write(&self, buf: &[u8]) -> Result<usize> {
+        self.io.io(WRITABLE, |mut stream| stream.write(buf)).await
+    }
+
+    pub async fn write_all(&self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            let n = self.write(buf).await?;
+            if n == 0 {
+                return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+            }
+            buf = &buf[n..];
+        }
+        Ok(())
+    }
+
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        Ok(self.io.source.peer_addr()?)
+    }
+}
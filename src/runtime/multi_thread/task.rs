@@ -0,0 +1,133 @@
+use std::{
+    panic::{AssertUnwindSafe, Location, catch_unwind},
+    pin::Pin,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicU8, Ordering},
+    },
+    task::{Context, Poll, Wake, Waker},
+};
+
+use super::Shared;
+
+// 任务的调度状态
+const IDLE: u8 = 0;
+// 已进入某个运行队列
+const SCHEDULED: u8 = 1;
+const RUNNING: u8 = 2;
+// 执行期间被唤醒，本次poll结束后重新进入运行队列
+const NOTIFIED: u8 = 3;
+const DONE: u8 = 4;
+
+type BoxedTask = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/*
+ * 多线程运行时中的任务
+ * 任务不绑定工作线程：每次被唤醒时进入唤醒方所在工作线程的队列（非工作线程唤醒时进入全局队列），可被任意工作线程窃取并执行
+ * 调度状态保证同一时刻只有一个工作线程在poll该任务
+ */
+pub(super) struct Task {
+    pub(super) id: u64,
+    future: Mutex<Option<BoxedTask>>,
+    state: AtomicU8,
+    shared: Weak<Shared>,
+    location: &'static Location<'static>,
+}
+
+impl Task {
+    pub(super) fn new(
+        id: u64,
+        future: BoxedTask,
+        shared: &Arc<Shared>,
+        location: &'static Location<'static>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            id,
+            future: Mutex::new(Some(future)),
+            state: AtomicU8::new(SCHEDULED),
+            shared: Arc::downgrade(shared),
+            location,
+        })
+    }
+
+    // 在工作线程中执行一次poll，执行期间被唤醒时重新进入当前工作线程的队列
+    pub(super) fn run(self: Arc<Self>, shared: &Shared) {
+        self.state.store(RUNNING, Ordering::SeqCst);
+        let waker = Waker::from(self.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut future = self.future.lock().unwrap();
+        let Some(fut) = future.as_mut() else {
+            return;
+        };
+        let done = match catch_unwind(AssertUnwindSafe(|| fut.as_mut().poll(&mut cx))) {
+            Ok(Poll::Ready(())) => true,
+            Ok(Poll::Pending) => false,
+            Err(_) => {
+                log::error!("task spawned at {} panicked", self.location);
+                true
+            }
+        };
+
+        if done {
+            // 释放future时任务句柄得到结果（或被取消）
+            future.take();
+            drop(future);
+            self.state.store(DONE, Ordering::SeqCst);
+            shared.remove_task(self.id);
+            return;
+        }
+        drop(future);
+
+        if self
+            .state
+            .compare_exchange(RUNNING, IDLE, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            self.state.store(SCHEDULED, Ordering::SeqCst);
+            shared.schedule(self);
+        }
+    }
+
+    // 运行时停止时释放尚未完成的future
+    pub(super) fn cancel(&self) {
+        self.state.store(DONE, Ordering::SeqCst);
+        self.future.lock().unwrap().take();
+    }
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        loop {
+            match self.state.load(Ordering::SeqCst) {
+                IDLE => {
+                    if self
+                        .state
+                        .compare_exchange(IDLE, SCHEDULED, Ordering::SeqCst, Ordering::SeqCst)
+                        .is_ok()
+                    {
+                        // 运行时已释放时任务随Waker一起释放
+                        if let Some(shared) = self.shared.upgrade() {
+                            shared.schedule(self.clone());
+                        }
+                        return;
+                    }
+                }
+                RUNNING => {
+                    if self
+                        .state
+                        .compare_exchange(RUNNING, NOTIFIED, Ordering::SeqCst, Ordering::SeqCst)
+                        .is_ok()
+                    {
+                        return;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time,
+};
+
+use super::{Shared, context};
+
+/// 多线程运行时中的定时器，到期前可在任意工作线程上被poll
+/// 使用系统时钟，不受time::pause影响
+pub fn sleep(dur: time::Duration) -> Sleep {
+    sleep_until(time::Instant::now() + dur)
+}
+
+pub fn sleep_until(deadline: time::Instant) -> Sleep {
+    Sleep {
+        deadline,
+        timer: None,
+    }
+}
+
+pub struct Sleep {
+    deadline: time::Instant,
+    // 首次poll时注册到驱动中
+    timer: Option<(Arc<Shared>, u64)>,
+}
+
+impl Sleep {
+    pub fn deadline(&self) -> time::Instant {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if time::Instant::now() >= self.deadline {
+            if let Some((shared, id)) = self.timer.take() {
+                shared.driver.remove_timer(self.deadline, id);
+            }
+            return Poll::Ready(());
+        }
+
+        let deadline = self.deadline;
+        match self.timer.as_ref() {
+            // 任务可能已迁移到其它工作线程，需要更新Waker
+            Some((shared, id)) => shared.driver.update_timer(deadline, *id, cx.waker()),
+            None => {
+                let shared = context();
+                let id = shared.driver.add_timer(deadline, cx.waker().clone());
+                self.timer.replace((shared, id));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some((shared, id)) = self.timer.take() {
+            shared.driver.remove_timer(self.deadline, id);
+        }
+    }
+}
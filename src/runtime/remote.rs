@@ -0,0 +1,342 @@
+use std::{
+    cell::Cell,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Poll, Waker},
+};
+
+use crate::{
+    result::{ErrorType, Result},
+    runtime::try_remote,
+    task::TaskStatus,
+};
+
+// io_event的token为堆上地址，不会与此值冲突
+pub(crate) const REMOTE_TOKEN: mio::Token = mio::Token(usize::MAX);
+
+// 下一个线程标识，单调递增，不会像线程局部变量的地址那样在线程退出后被复用
+static NEXT_THREAD_MARK: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    // 首次使用时分配，0表示未分配；无析构函数，线程退出过程中也可以访问
+    static THREAD_MARK: Cell<usize> = const { Cell::new(0) };
+}
+
+// 当前线程的标识
+pub(crate) fn thread_mark() -> usize {
+    THREAD_MARK.with(|mark| {
+        if mark.get() == 0 {
+            mark.set(NEXT_THREAD_MARK.fetch_add(1, Ordering::Relaxed));
+        }
+        mark.get()
+    })
+}
+
+// 其它线程对Waker的操作，按投递顺序在所属运行时的线程中执行
+enum RemoteOp {
+    Wake(Waker),
+    Drop(Waker),
+}
+
+/*
+ * 其它线程clone出的Waker副本还未计入引用计数
+ * 由所属线程通过retain函数补充，任何释放操作之前都需要先补充
+ */
+struct Retain {
+    data: usize,
+    retain: fn(*const ()),
+}
+
+struct RemoteInner {
+    waker: mio::Waker,
+    queue: Mutex<Vec<RemoteOp>>,
+    retains: Mutex<Vec<Retain>>,
+    pending_retains: AtomicUsize,
+    // 在其它线程上执行、完成后会唤醒当前运行时的工作数量
+    outstanding: AtomicUsize,
+}
+
+/*
+ * 运行时可跨线程使用的句柄
+ * 通过mio::Waker打断运行时阻塞中的poll，并将其它线程对Waker的操作转交给运行时所在的线程执行
+ * 队列中的Waker持有RemoteHandle，所属运行时销毁后未处理的操作会随之泄露
+ */
+#[derive(Clone)]
+pub(crate) struct RemoteHandle(Arc<RemoteInner>);
+
+impl RemoteHandle {
+    pub(crate) fn new(registry: &mio::Registry) -> Result<Self> {
+        Ok(Self(Arc::new(RemoteInner {
+            waker: mio::Waker::new(registry, REMOTE_TOKEN)?,
+            queue: Mutex::new(Vec::new()),
+            retains: Mutex::new(Vec::new()),
+            pending_retains: AtomicUsize::new(0),
+            outstanding: AtomicUsize::new(0),
+        })))
+    }
+
+    // 唤醒运行时的事件循环
+    pub(crate) fn unpark(&self) {
+        if let Err(e) = self.0.waker.wake() {
+            log::warn!("remote unpark failed: {}", e);
+        }
+    }
+
+    // 登记一项在其它线程上执行的工作，返回值释放时表示工作已结束
+    pub(crate) fn track_work(&self) -> RemoteWork {
+        self.0.outstanding.fetch_add(1, Ordering::AcqRel);
+        RemoteWork(self.clone())
+    }
+
+    // 是否还有未结束的其它线程上的工作，或未处理的远程操作
+    pub(crate) fn busy(&self) -> bool {
+        self.0.outstanding.load(Ordering::Acquire) > 0 || !self.0.queue.lock().unwrap().is_empty()
+    }
+
+    pub(crate) fn push_wake(&self, waker: Waker) {
+        self.push(RemoteOp::Wake(waker));
+    }
+
+    pub(crate) fn push_drop(&self, waker: Waker) {
+        self.push(RemoteOp::Drop(waker));
+    }
+
+    fn push(&self, op: RemoteOp) {
+        self.0.queue.lock().unwrap().push(op);
+        self.unpark();
+    }
+
+    pub(crate) fn push_retain(&self, data: *const (), retain: fn(*const ())) {
+        self.0.retains.lock().unwrap().push(Retain {
+            data: data as usize,
+            retain,
+        });
+        self.0.pending_retains.fetch_add(1, Ordering::Release);
+    }
+
+    // 只能在所属运行时的线程中调用
+    pub(crate) fn apply_retains(&self) {
+        if self.0.pending_retains.load(Ordering::Acquire) == 0 {
+            return;
+        }
+
+        let retains = std::mem::take(&mut *self.0.retains.lock().unwrap());
+        self.0
+            .pending_retains
+            .fetch_sub(retains.len(), Ordering::Release);
+        for Retain { data, retain } in retains {
+            retain(data as *const ());
+        }
+    }
+
+    // 只能在所属运行时的线程中调用，返回其它线程唤醒的Waker
+    pub(crate) fn take_wakers(&self) -> Vec<Waker> {
+        self.apply_retains();
+        let ops = std::mem::take(&mut *self.0.queue.lock().unwrap());
+        let mut wakers = Vec::new();
+        for op in ops {
+            match op {
+                RemoteOp::Wake(waker) => wakers.push(waker),
+                RemoteOp::Drop(waker) => drop(waker),
+            }
+        }
+        wakers
+    }
+}
+
+/*
+ * 其它线程上的工作登记
+ * 在工作的结果投递之后才释放，释放时再次唤醒运行时，保证运行时能重新检查是否还有未结束的工作
+ */
+pub(crate) struct RemoteWork(RemoteHandle);
+
+impl Drop for RemoteWork {
+    fn drop(&mut self) {
+        self.0.0.outstanding.fetch_sub(1, Ordering::AcqRel);
+        self.0.unpark();
+    }
+}
+
+struct RemoteJoinState<T> {
+    output: Option<Result<T>>,
+    status: TaskStatus,
+    waiter: Option<Waker>,
+}
+
+// 创建可跨线程完成的任务句柄，Completion由执行任务的一方持有
+// 在运行时中创建时，任务会登记为当前运行时的远程工作，暂停的时钟在其结束前不会自动推进
+pub(crate) fn remote_join<T>() -> (Completion<T>, RemoteJoinHandle<T>) {
+    let state = Arc::new(Mutex::new(RemoteJoinState {
+        output: None,
+        status: TaskStatus::Running,
+        waiter: None,
+    }));
+    let work = try_remote().map(|remote| remote.track_work());
+    (
+        Completion {
+            state: state.clone(),
+            _work: work,
+        },
+        RemoteJoinHandle { state },
+    )
+}
+
+// 任务完成时写入输出，在完成前被释放（运行时停止）时标记为取消
+pub(crate) struct Completion<T> {
+    state: Arc<Mutex<RemoteJoinState<T>>>,
+    // 在Drop之后释放，即输出投递之后
+    _work: Option<RemoteWork>,
+}
+
+impl<T> Completion<T> {
+    pub(crate) fn complete(self, output: Result<T>) {
+        self.finish(Some(output));
+    }
+
+    fn finish(&self, output: Option<Result<T>>) {
+        let waiter = {
+            let mut state = self.state.lock().unwrap();
+            if state.status.finished() {
+                return;
+            }
+
+            state.status = match output {
+                Some(output) => {
+                    state.output.replace(output);
+                    TaskStatus::Completed
+                }
+                None => TaskStatus::Cancelled,
+            };
+            state.waiter.take()
+        };
+
+        if let Some(waiter) = waiter {
+            waiter.wake();
+        }
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        self.finish(None);
+    }
+}
+
+/// 在其它线程上执行的任务的句柄，可在任意运行时中await
+pub struct RemoteJoinHandle<T> {
+    state: Arc<Mutex<RemoteJoinState<T>>>,
+}
+
+impl<T> RemoteJoinHandle<T> {
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().status.finished()
+    }
+}
+
+impl<T> Future for RemoteJoinHandle<T> {
+    type Output = Result<T>;
+
+    fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.status {
+            TaskStatus::Running => {
+                if !state
+                    .waiter
+                    .as_ref()
+                    .is_some_and(|w| w.will_wake(cx.waker()))
+                {
+                    state.waiter.replace(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+            TaskStatus::Cancelled => Poll::Ready(Err(ErrorType::Cancelled.into())),
+            TaskStatus::Completed => Poll::Ready(
+                state
+                    .output
+                    .take()
+                    .unwrap_or_else(|| Err("task output has been taken".into())),
+            ),
+        }
+    }
+}
+
+impl<T> Drop for RemoteJoinHandle<T> {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().waiter.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        pin::Pin,
+        rc::Rc,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        task::{Context, Poll},
+        thread, time,
+    };
+
+    use crate::sleep;
+
+    // 在其它线程中clone、唤醒、释放当前任务的Waker
+    struct ForeignWake {
+        woken: Arc<AtomicUsize>,
+        started: bool,
+        _flag: DropFlag,
+    }
+
+    struct DropFlag(Rc<Cell<bool>>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    impl Future for ForeignWake {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            if self.woken.load(Ordering::Acquire) == 4 {
+                return Poll::Ready(());
+            }
+            if !self.started {
+                self.started = true;
+                for _ in 0..4 {
+                    let (waker, woken) = (cx.waker().clone(), self.woken.clone());
+                    thread::spawn(move || {
+                        thread::sleep(time::Duration::from_millis(20));
+                        let cloned = waker.clone();
+                        woken.fetch_add(1, Ordering::Release);
+                        cloned.wake_by_ref();
+                        drop(cloned);
+                        waker.wake();
+                    });
+                }
+            }
+            Poll::Pending
+        }
+    }
+
+    #[rt_entry::test]
+    async fn test_foreign_wake() {
+        let dropped = Rc::new(Cell::new(false));
+        let handle = spawn!(ForeignWake {
+            woken: Arc::new(AtomicUsize::new(0)),
+            started: false,
+            _flag: DropFlag(dropped.clone()),
+        });
+        // 没有其它io与定时器，只能依赖其它线程的唤醒
+        handle.await.unwrap();
+
+        // 其它线程上的Waker全部释放后任务也随之释放
+        sleep(time::Duration::from_millis(50)).await;
+        assert!(dropped.get());
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    cell::{Cell, UnsafeCell},
+    mem, ptr,
+    sync::{
+        Arc, Once,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering, compiler_fence},
+    },
+    thread, time,
+};
+
+use backtrace::{Backtrace, BacktraceFrame, Frame};
+use signal_hook::{consts, low_level::register};
+
+use crate::result::Result;
+
+// 采集调用栈的最大帧数，信号处理函数中不能分配内存，只写入预先分配的空间
+const MAX_FRAMES: usize = 128;
+
+// 请求运行时线程采集调用栈的信号，SIGURG默认被忽略，不会影响未注册处理函数的线程
+const SAMPLE_SIGNAL: i32 = consts::SIGURG;
+
+static REGISTER_ONCE: Once = Once::new();
+
+thread_local! {
+    // 当前线程的调用栈采集器，只在信号处理函数中读取
+    static SAMPLER: Cell<*const StackSampler> = const { Cell::new(ptr::null()) };
+}
+
+struct WatchdogState {
+    origin: time::Instant,
+    // 当前一轮事件循环开始的时间（距origin的微秒数+1），0表示事件循环空闲
+    turn_started: AtomicU64,
+    // 正在执行的poll的序号，0表示不在poll中
+    polling: AtomicU64,
+    // 正在执行的poll开始的时间，表示方式同turn_started
+    poll_started: AtomicU64,
+    // 已请求采集调用栈的poll序号
+    sample_poll: AtomicU64,
+    // 检测到的事件循环卡顿次数
+    stalls: AtomicUsize,
+    stop: AtomicBool,
+}
+
+impl WatchdogState {
+    fn elapsed_us(&self) -> u64 {
+        self.origin.elapsed().as_micros() as u64 + 1
+    }
+}
+
+// 运行时线程，用于向其发送采集调用栈的信号
+struct RuntimeThread(libc::pthread_t);
+
+unsafe impl Send for RuntimeThread {}
+
+/*
+ * 慢poll的调用栈，由看门狗线程发送信号后在运行时线程上的信号处理函数中写入
+ * 写入与读取都发生在运行时线程上，读取前会先修改polling，使之后到达的信号不再写入
+ */
+struct StackSampler {
+    state: Arc<WatchdogState>,
+    frames: UnsafeCell<Vec<Frame>>,
+}
+
+// 信号处理函数：只在请求采集的poll仍在执行时展开调用栈，且不分配内存
+fn sample_stack() {
+    let sampler = SAMPLER.with(Cell::get);
+    if sampler.is_null() {
+        return;
+    }
+    let sampler = unsafe { &*sampler };
+    let polling = sampler.state.polling.load(Ordering::SeqCst);
+    if polling == 0 || polling != sampler.state.sample_poll.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let frames = unsafe { &mut *sampler.frames.get() };
+    if !frames.is_empty() {
+        return;
+    }
+    unsafe {
+        backtrace::trace_unsynchronized(|frame| {
+            frames.push(frame.clone());
+            frames.len() < frames.capacity()
+        });
+    }
+}
+
+/*
+ * 事件循环的看门狗，在独立线程中检查事件循环是否长时间停留在同一轮中
+ * 等待io与定时器的阻塞不算作卡顿，只有执行就绪任务的耗时超过limit时才会报告，每次卡顿只报告一次
+ * 设置了慢poll阈值时，还会在单次poll超过阈值且仍在执行时请求运行时线程采集调用栈，供poll结束后打印
+ */
+pub(crate) struct Watchdog {
+    state: Arc<WatchdogState>,
+    sampler: Option<Box<StackSampler>>,
+    // 之前在当前线程注册的采集器，释放时恢复
+    prev_sampler: *const StackSampler,
+    // 本线程上poll的序号
+    next_poll: Cell<u64>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub(crate) fn new(
+        loop_limit: Option<time::Duration>,
+        slow_poll: Option<time::Duration>,
+    ) -> Result<Self> {
+        let state = Arc::new(WatchdogState {
+            origin: time::Instant::now(),
+            turn_started: AtomicU64::new(0),
+            polling: AtomicU64::new(0),
+            poll_started: AtomicU64::new(0),
+            sample_poll: AtomicU64::new(0),
+            stalls: AtomicUsize::new(0),
+            stop: AtomicBool::new(false),
+        });
+
+        let sampler = slow_poll.map(|_| {
+            REGISTER_ONCE.call_once(|| unsafe {
+                register(SAMPLE_SIGNAL, sample_stack).unwrap();
+            });
+            Box::new(StackSampler {
+                state: state.clone(),
+                frames: UnsafeCell::new(Vec::with_capacity(MAX_FRAMES)),
+            })
+        });
+        let prev_sampler = match sampler.as_ref() {
+            Some(sampler) => SAMPLER.with(|s| s.replace(&**sampler)),
+            None => ptr::null(),
+        };
+
+        let runtime_thread = thread::current().name().unwrap_or("unnamed").to_owned();
+        let target = RuntimeThread(unsafe { libc::pthread_self() });
+        let s = state.clone();
+        let thread = thread::Builder::new()
+            .name("mini-runtime-watchdog".to_owned())
+            .spawn(move || watch(&s, loop_limit, slow_poll, &runtime_thread, target))?;
+        Ok(Self {
+            state,
+            sampler,
+            prev_sampler,
+            next_poll: Cell::new(0),
+            thread: Some(thread),
+        })
+    }
+
+    pub(crate) fn turn_start(&self) {
+        self.state
+            .turn_started
+            .store(self.state.elapsed_us(), Ordering::Release);
+    }
+
+    pub(crate) fn turn_end(&self) {
+        self.state.turn_started.store(0, Ordering::Release);
+    }
+
+    // 开始一次poll，返回之前正在执行的poll，在嵌套的poll结束后恢复
+    pub(crate) fn poll_start(&self) -> (u64, u64) {
+        let poll = self.next_poll.get() + 1;
+        self.next_poll.set(poll);
+        // 先写入开始时间，看门狗线程读到新的序号时开始时间同样是新的
+        let started = self
+            .state
+            .poll_started
+            .swap(self.state.elapsed_us(), Ordering::SeqCst);
+        (self.state.polling.swap(poll, Ordering::SeqCst), started)
+    }
+
+    // 结束一次poll，返回在poll执行期间采集到的调用栈
+    pub(crate) fn poll_end(&self, prev: (u64, u64)) -> Option<Backtrace> {
+        let poll = self.state.polling.swap(prev.0, Ordering::SeqCst);
+        self.state.poll_started.store(prev.1, Ordering::SeqCst);
+        // 信号处理函数在本线程上执行，保证读取调用栈不会被重排到修改polling之前
+        compiler_fence(Ordering::SeqCst);
+
+        let sampler = self.sampler.as_ref()?;
+        if self.state.sample_poll.load(Ordering::SeqCst) != poll {
+            return None;
+        }
+        let frames = unsafe { &mut *sampler.frames.get() };
+        if frames.is_empty() {
+            return None;
+        }
+        let frames = mem::replace(frames, Vec::with_capacity(MAX_FRAMES));
+        Some(Backtrace::from(
+            frames
+                .into_iter()
+                .map(BacktraceFrame::from)
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    pub(crate) fn stalls(&self) -> usize {
+        self.state.stalls.load(Ordering::Relaxed)
+    }
+}
+
+fn watch(
+    state: &WatchdogState,
+    loop_limit: Option<time::Duration>,
+    slow_poll: Option<time::Duration>,
+    runtime_thread: &str,
+    target: RuntimeThread,
+) {
+    let interval = loop_limit
+        .into_iter()
+        .chain(slow_poll)
+        .min()
+        .map_or(time::Duration::from_millis(1), |limit| limit / 2)
+        .max(time::Duration::from_millis(1));
+    let mut reported = 0;
+    while !state.stop.load(Ordering::Acquire) {
+        thread::park_timeout(interval);
+
+        if let Some(limit) = loop_limit {
+            let started = state.turn_started.load(Ordering::Acquire);
+            if started != 0 && started != reported {
+                let stalled = state.elapsed_us().saturating_sub(started);
+                if stalled >= limit.as_micros() as u64 {
+                    reported = started;
+                    state.stalls.fetch_add(1, Ordering::Relaxed);
+                    log::warn!(
+                        "event loop on thread <{}> has not turned for {:?}, a task may be blocking",
+                        runtime_thread,
+                        time::Duration::from_micros(stalled)
+                    );
+                }
+            }
+        }
+
+        // 每次poll只请求一次采集，超过阈值时poll仍在执行，调用栈即为阻塞的位置
+        if let Some(threshold) = slow_poll {
+            let polling = state.polling.load(Ordering::SeqCst);
+            let started = state.poll_started.load(Ordering::SeqCst);
+            if polling != 0
+                && polling != state.sample_poll.load(Ordering::SeqCst)
+                && state.elapsed_us().saturating_sub(started) >= threshold.as_micros() as u64
+            {
+                state.sample_poll.store(polling, Ordering::SeqCst);
+                unsafe {
+                    libc::pthread_kill(target.0, SAMPLE_SIGNAL);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+        if self.sampler.is_some() {
+            SAMPLER.with(|s| s.set(self.prev_sampler));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time};
+
+    use crate::runtime::{Runtime, metrics, spawn_named, watchdog::Watchdog};
+
+    #[test]
+    fn test_slow_poll() {
+        let rt = Runtime::builder()
+            .handle_signal(false)
+            .slow_poll_threshold(time::Duration::from_millis(20))
+            .watchdog(time::Duration::from_millis(50))
+            .build()
+            .unwrap();
+
+        let metrics = rt
+            .block_on(async {
+                // 模拟在事件循环中执行同步阻塞操作
+                spawn_named("blocking", async {
+                    thread::sleep(time::Duration::from_millis(200));
+                })
+                .await
+                .unwrap();
+                spawn_named("fast", async {}).await.unwrap();
+                metrics()
+            })
+            .unwrap();
+
+        assert_eq!(metrics.slow_polls(), 1);
+        assert_eq!(metrics.loop_stalls(), 1);
+    }
+
+    #[test]
+    fn test_slow_poll_backtrace() {
+        #[inline(never)]
+        fn blocking_handler() {
+            thread::sleep(time::Duration::from_millis(100));
+        }
+
+        let watchdog = Watchdog::new(None, Some(time::Duration::from_millis(10))).unwrap();
+
+        // 未超过阈值的poll不采集调用栈
+        let prev = watchdog.poll_start();
+        assert!(watchdog.poll_end(prev).is_none());
+
+        let prev = watchdog.poll_start();
+        blocking_handler();
+        let mut bt = watchdog.poll_end(prev).unwrap();
+        bt.resolve();
+        assert!(format!("{:?}", bt).contains("blocking_handler"));
+    }
+}
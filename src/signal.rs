@@ -1,72 +1,105 @@
 use std::{
     cell::RefCell,
+    io::{self, Read},
+    os::unix::net::UnixStream,
     sync::{
         Once,
-        atomic::{AtomicBool, AtomicU64, Ordering},
+        atomic::{AtomicUsize, Ordering},
     },
     time,
 };
 
-use lazy_static::lazy_static;
-use signal_hook::{consts, low_level::register};
+use signal_hook::{
+    SigId, consts,
+    low_level::{pipe, register, unregister},
+};
 
 use crate::{
-    helper::{UPSafeCell, yield_here},
-    runtime::{add_waker, force_stop, spawn},
+    helper::yield_here,
+    result::Result,
+    runtime::{add_waker, force_stop, rt_stopped, set_grace_period, spawn, stop_waiters},
     sleep,
     task::waker_ext::{WakerSet, WakerSetDropper},
 };
 
-pub static STOPPED: AtomicBool = AtomicBool::new(false);
+// 收到的停止信号数量。信号处理函数中只做计数，由各个运行时在事件循环中感知并执行停止动作
+static SIGNAL_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// 收到的任务转储信号（SIGUSR1）数量
+static DUMP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+static REGISTER_ONCE: Once = Once::new();
 
-// 停止后最大等待时长（ms）。默认1000ms
-static MAX_WAIT_DURATION_MS: AtomicU64 = AtomicU64::new(1000);
+// 信号自管道在Poller中的token，与REMOTE_TOKEN同样不会与io_event冲突
+pub(crate) const SIGNAL_TOKEN: mio::Token = mio::Token(usize::MAX - 1);
 
 /*
- * 当前等待的在stop时执行的waker。在stop时会被移动到ready_tasks列表中并被执行
- * 由于StopWaker通常是和select!同时使用的，因此需要在删除时也同时移除这里的waker，避免二次触发waker
+ * 信号到达时由信号处理函数向自管道写入数据，唤醒阻塞在poll中的运行时
+ * 每个响应信号的运行时各自持有一个，释放时注销
  */
-lazy_static! {
-    static ref STOP_WAITERS: UPSafeCell<WakerSet> = UPSafeCell::new(WakerSet::default());
+pub(crate) struct SignalPipe {
+    reader: mio::net::UnixStream,
+    ids: Vec<SigId>,
 }
 
-// lazy_static! {
-//     static ref STOP_NOTIFIER: UPSafeCell<Notifier> = UPSafeCell::new(Notifier::new());
-// }
+impl SignalPipe {
+    pub(crate) fn new(registry: &mio::Registry) -> Result<Self> {
+        let (reader, writer) = UnixStream::pair()?;
+        reader.set_nonblocking(true)?;
+        let mut reader = mio::net::UnixStream::from_std(reader);
+        registry.register(&mut reader, SIGNAL_TOKEN, mio::Interest::READABLE)?;
+
+        let ids = [consts::SIGINT, consts::SIGTERM, consts::SIGUSR1]
+            .into_iter()
+            .map(|signal| pipe::register(signal, writer.try_clone()?))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Self { reader, ids })
+    }
 
-// #[allow(unused)]
-// pub(crate) fn wait_stop_notifier() -> BoxedFuture<'static, ()> {
-//     let mut waiter = STOP_NOTIFIER.exclusive_access().waiter();
+    pub(crate) fn drain(&mut self) {
+        let mut buf = [0u8; 64];
+        while matches!(self.reader.read(&mut buf), Ok(n) if n > 0) {}
+    }
+}
 
-//     Box::pin(async move {
-//         waiter.wait().await;
-//         Ok(())
-//     })
-// }
+impl Drop for SignalPipe {
+    fn drop(&mut self) {
+        for id in self.ids.drain(..) {
+            unregister(id);
+        }
+    }
+}
 
-#[allow(unused)]
+/*
+ * 当前等待的在stop时执行的waker由各个运行时自行保存。在stop时会被移动到ready_tasks列表中并被执行
+ * 由于StopWaker通常是和select!同时使用的，因此需要在删除时也同时移除这里的waker，避免二次触发waker
+ */
+
+// 设置当前运行时停止后的最大等待时长
 pub(crate) fn set_max_wait_duration(dur: time::Duration) {
-    MAX_WAIT_DURATION_MS.store(dur.as_millis() as u64, Ordering::Relaxed);
+    set_grace_period(dur);
 }
 
-fn get_max_wait_duration() -> time::Duration {
-    time::Duration::from_millis(MAX_WAIT_DURATION_MS.load(Ordering::Relaxed))
+pub(crate) fn signal_count() -> usize {
+    SIGNAL_COUNT.load(Ordering::Acquire)
 }
 
-fn stop_action() {
+pub(crate) fn dump_count() -> usize {
+    DUMP_COUNT.load(Ordering::Acquire)
+}
+
+pub(crate) fn stop_action(waiters: &WakerSet, max_wait_duration: time::Duration) {
     log::warn!("signal handler: catch sigint");
-    STOPPED.store(true, Ordering::Relaxed);
     // 将需要在终止时运行的任务放入待执行的任务队列中
-    for waker_ext in STOP_WAITERS.exclusive_access().drain() {
+    for waker_ext in waiters.drain() {
         add_waker(waker_ext.into());
     }
     log::info!("stop wakers to run");
 
-    spawn(async {
+    spawn(async move {
         // 等待一定时长后强制停止，但又希望在只剩当前一个waker的时候也及时停止
         // 所以需要配合Runtime::finish()一起使用
-        sleep(get_max_wait_duration()).await;
-        // STOP_NOTIFIER.exclusive_access().notify_all();
+        sleep(max_wait_duration).await;
         // 保证全部被通知任务的执行
         yield_here().await;
         log::warn!("force stop");
@@ -74,16 +107,29 @@ fn stop_action() {
     });
 }
 
+// 注册信号处理函数（进程内只注册一次）
 pub fn signal_handler() {
-    unsafe {
-        register(consts::SIGINT, stop_action).unwrap();
-
-        register(consts::SIGTERM, stop_action).unwrap();
-    }
+    REGISTER_ONCE.call_once(|| unsafe {
+        register(consts::SIGINT, || {
+            SIGNAL_COUNT.fetch_add(1, Ordering::Release);
+        })
+        .unwrap();
+
+        register(consts::SIGTERM, || {
+            SIGNAL_COUNT.fetch_add(1, Ordering::Release);
+        })
+        .unwrap();
+
+        // 打印各个运行时中的全部任务，用于排查卡住的任务
+        register(consts::SIGUSR1, || {
+            DUMP_COUNT.fetch_add(1, Ordering::Release);
+        })
+        .unwrap();
+    });
 }
 
 pub fn stopped() -> bool {
-    STOPPED.load(Ordering::Acquire)
+    rt_stopped()
 }
 
 // 设置停止事件的唤醒器，在ctrl-c的时候会触发
@@ -112,11 +158,9 @@ impl Future for StopWakerGuard {
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
         self.once.call_once(|| {
-            self._dropper.borrow_mut().replace(
-                STOP_WAITERS
-                    .exclusive_access()
-                    .add_with_dropper(cx.waker().clone().into()),
-            );
+            self._dropper
+                .borrow_mut()
+                .replace(stop_waiters().add_with_dropper(cx.waker().clone().into()));
         });
         if stopped() {
             add_waker(cx.waker().clone());
@@ -0,0 +1,265 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    rc::Rc,
+};
+
+use crate::{
+    result::{ErrorType, Result},
+    runtime::{add_waker, dump::WaitOn, wait_on},
+    task::waker_ext::{WakerSet, WakerSetDropper},
+};
+
+struct Shared<T> {
+    // 最近的capacity条数据，超出后覆盖最旧的数据
+    buffer: RefCell<VecDeque<T>>,
+    capacity: usize,
+    // 下一条数据的序号，buffer中最旧数据的序号为tail - buffer.len()
+    tail: Cell<u64>,
+    senders: Cell<usize>,
+    receivers: Cell<usize>,
+    // 等待新数据的接收端
+    wakers: WakerSet,
+}
+
+impl<T> Shared<T> {
+    fn head(&self) -> u64 {
+        self.tail.get() - self.buffer.borrow().len() as u64
+    }
+
+    fn wake_all(&self) {
+        for waker_ext in self.wakers.drain() {
+            add_waker(waker_ext.into());
+        }
+    }
+
+    fn new_receiver(self: &Rc<Self>, next: u64) -> Receiver<T> {
+        self.receivers.set(self.receivers.get() + 1);
+        Receiver {
+            shared: self.clone(),
+            next,
+        }
+    }
+}
+
+/*
+ * 广播通道，每条数据会被当前全部接收端各收到一次
+ * 通道只保留最近的capacity条数据，接收端落后超过capacity时跳过被覆盖的数据并返回ErrorType::Lagged
+ */
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(
+        capacity > 0,
+        "broadcast channel capacity must be greater than 0"
+    );
+    let shared = Rc::new(Shared {
+        buffer: RefCell::new(VecDeque::with_capacity(capacity)),
+        capacity,
+        tail: Cell::new(0),
+        senders: Cell::new(1),
+        receivers: Cell::new(0),
+        wakers: WakerSet::default(),
+    });
+    let receiver = shared.new_receiver(0);
+    (Sender { shared }, receiver)
+}
+
+pub struct Sender<T> {
+    shared: Rc<Shared<T>>,
+}
+
+impl<T: Clone> Sender<T> {
+    // 返回收到数据的接收端数量，没有接收端时返回ErrorType::Closed
+    pub fn send(&self, value: T) -> Result<usize> {
+        let receivers = self.shared.receivers.get();
+        if receivers == 0 {
+            return Err(ErrorType::Closed.into());
+        }
+
+        let old = {
+            let mut buffer = self.shared.buffer.borrow_mut();
+            let old = match buffer.len() >= self.shared.capacity {
+                true => buffer.pop_front(),
+                false => None,
+            };
+            buffer.push_back(value);
+            old
+        };
+        // 被覆盖的数据在借用结束后释放
+        drop(old);
+        self.shared.tail.set(self.shared.tail.get() + 1);
+        self.shared.wake_all();
+        Ok(receivers)
+    }
+
+    // 新的接收端只会收到订阅之后发送的数据
+    pub fn subscribe(&self) -> Receiver<T> {
+        self.shared.new_receiver(self.shared.tail.get())
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.shared.receivers.get()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.set(self.shared.senders.get() + 1);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.shared.senders.set(self.shared.senders.get() - 1);
+        // 全部发送端释放后，接收端在取完剩余数据后结束
+        if self.shared.senders.get() == 0 {
+            self.shared.wake_all();
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    shared: Rc<Shared<T>>,
+    // 下一条要接收的数据的序号
+    next: u64,
+}
+
+impl<T: Clone> Receiver<T> {
+    /*
+     * 没有新数据时等待
+     * 落后时返回ErrorType::Lagged并跳到最旧的数据，全部发送端释放且数据取完后返回ErrorType::Closed
+     */
+    pub fn recv(&mut self) -> RecvWaiter<'_, T> {
+        RecvWaiter {
+            receiver: self,
+            _dropper: None,
+        }
+    }
+
+    // 没有新数据时返回ErrorType::Blocked
+    pub fn try_recv(&mut self) -> Result<T> {
+        let head = self.shared.head();
+        if self.next < head {
+            let lagged = head - self.next;
+            self.next = head;
+            return Err(ErrorType::Lagged(lagged).into());
+        }
+
+        if self.next < self.shared.tail.get() {
+            let value = self.shared.buffer.borrow()[(self.next - head) as usize].clone();
+            self.next += 1;
+            return Ok(value);
+        }
+
+        match self.shared.senders.get() {
+            0 => Err(ErrorType::Closed.into()),
+            _ => Err(ErrorType::Blocked.into()),
+        }
+    }
+
+    // 尚未接收的数据数量，包括已被覆盖的数据
+    pub fn len(&self) -> usize {
+        (self.shared.tail.get() - self.next) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    // 新的接收端从当前位置开始接收
+    fn clone(&self) -> Self {
+        self.shared.new_receiver(self.next)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.receivers.set(self.shared.receivers.get() - 1);
+    }
+}
+
+pub struct RecvWaiter<'a, T> {
+    receiver: &'a mut Receiver<T>,
+    _dropper: Option<WakerSetDropper>,
+}
+
+impl<T: Clone> Future for RecvWaiter<'_, T> {
+    type Output = Result<T>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        match this.receiver.try_recv() {
+            Err(e) if e.is_blocked() => {
+                let dropper = this
+                    .receiver
+                    .shared
+                    .wakers
+                    .add_with_dropper(cx.waker().clone().into());
+                this._dropper.replace(dropper);
+                wait_on(WaitOn::Channel);
+                std::task::Poll::Pending
+            }
+            result => std::task::Poll::Ready(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time;
+
+    use crate::{sleep, sync::broadcast::channel};
+
+    #[rt_entry::test]
+    async fn test_broadcast() {
+        let (tx, mut rx1) = channel(4);
+        let mut rx2 = tx.subscribe();
+        let receiver = spawn!(async move {
+            let mut received = Vec::new();
+            while let Ok(i) = rx2.recv().await {
+                received.push(i);
+            }
+            received
+        });
+
+        for i in 0..3 {
+            assert_eq!(tx.send(i).unwrap(), 2);
+            sleep(time::Duration::from_millis(1)).await;
+        }
+        drop(tx);
+        assert_eq!(receiver.await.unwrap(), [0, 1, 2]);
+        for i in 0..3 {
+            assert_eq!(rx1.recv().await.unwrap(), i);
+        }
+        assert!(rx1.recv().await.is_err_and(|e| e.is_closed()));
+    }
+
+    #[rt_entry::test]
+    async fn test_broadcast_lagged() {
+        let (tx, mut rx) = channel(2);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.len(), 5);
+
+        // 落后的接收端跳过被覆盖的数据
+        let err = rx.recv().await.unwrap_err();
+        assert!(matches!(
+            err.err_type(),
+            crate::result::ErrorType::Lagged(3)
+        ));
+        assert_eq!(rx.recv().await.unwrap(), 3);
+        assert_eq!(rx.try_recv().unwrap(), 4);
+        assert!(rx.try_recv().is_err_and(|e| e.is_blocked()));
+
+        drop(rx);
+        assert!(tx.send(5).is_err_and(|e| e.is_closed()));
+    }
+}
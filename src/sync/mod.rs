@@ -1,4 +1,9 @@
+pub mod broadcast;
+pub mod mpsc;
 pub mod mutex;
 pub mod notifier;
+pub mod oneshot;
+pub mod rwlock;
 pub mod semophore;
 pub mod wait_group;
+pub mod watch;
@@ -0,0 +1,378 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    rc::Rc,
+};
+
+use crate::{
+    result::{ErrorType, Result},
+    runtime::{add_waker, dump::WaitOn, wait_on},
+    task::waker_ext::{WakerSet, WakerSetDropper},
+};
+
+// 发送端与接收端共享的通道
+struct Chan<T> {
+    queue: RefCell<VecDeque<T>>,
+    // None表示无界通道
+    capacity: Option<usize>,
+    senders: Cell<usize>,
+    // 接收端被关闭或释放后不再接收新的数据
+    closed: Cell<bool>,
+    // 等待数据的接收端
+    recv_wakers: WakerSet,
+    // 等待空位的发送端
+    send_wakers: WakerSet,
+}
+
+impl<T> Chan<T> {
+    fn new(capacity: Option<usize>) -> Rc<Self> {
+        Rc::new(Self {
+            queue: RefCell::new(VecDeque::new()),
+            capacity,
+            senders: Cell::new(1),
+            closed: Cell::new(false),
+            recv_wakers: WakerSet::default(),
+            send_wakers: WakerSet::default(),
+        })
+    }
+
+    fn is_full(&self) -> bool {
+        self.capacity
+            .is_some_and(|capacity| self.queue.borrow().len() >= capacity)
+    }
+
+    fn try_send(&self, value: T) -> Result<()> {
+        if self.closed.get() {
+            return Err(ErrorType::Closed.into());
+        }
+        if self.is_full() {
+            return Err(ErrorType::Blocked.into());
+        }
+
+        self.queue.borrow_mut().push_back(value);
+        wake_one(&self.recv_wakers);
+        Ok(())
+    }
+
+    fn add_sender(&self) {
+        self.senders.set(self.senders.get() + 1);
+    }
+
+    fn drop_sender(&self) {
+        self.senders.set(self.senders.get() - 1);
+        // 全部发送端释放后，接收端在取完剩余数据后结束
+        if self.senders.get() == 0 {
+            wake_all(&self.recv_wakers);
+        }
+    }
+
+    fn close(&self) {
+        self.closed.set(true);
+        wake_all(&self.send_wakers);
+    }
+}
+
+fn wake_one(wakers: &WakerSet) {
+    if let Some(waker_ext) = wakers.pop() {
+        add_waker(waker_ext.into());
+    }
+}
+
+fn wake_all(wakers: &WakerSet) {
+    for waker_ext in wakers.drain() {
+        add_waker(waker_ext.into());
+    }
+}
+
+/// 有界通道，缓存的数据达到capacity时send会等待接收端取走数据
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "mpsc channel capacity must be greater than 0");
+    let chan = Chan::new(Some(capacity));
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// 无界通道，send不会等待
+pub fn unbounded_channel<T>() -> (UnboundedSender<T>, Receiver<T>) {
+    let chan = Chan::new(None);
+    (UnboundedSender { chan: chan.clone() }, Receiver { chan })
+}
+
+pub struct Sender<T> {
+    chan: Rc<Chan<T>>,
+}
+
+impl<T> Sender<T> {
+    // 通道已满时等待，接收端关闭时返回ErrorType::Closed
+    pub fn send(&self, value: T) -> SendWaiter<'_, T> {
+        SendWaiter {
+            chan: &self.chan,
+            value: Some(value),
+            _dropper: None,
+        }
+    }
+
+    // 通道已满时返回ErrorType::Blocked
+    pub fn try_send(&self, value: T) -> Result<()> {
+        self.chan.try_send(value)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.chan.closed.get()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.chan.add_sender();
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.chan.drop_sender();
+    }
+}
+
+pub struct SendWaiter<'a, T> {
+    chan: &'a Chan<T>,
+    // 发送成功后置空
+    value: Option<T>,
+    // 在select!等场景下可能在等待中被drop，需要保证在WakerSet中的正常释放
+    _dropper: Option<WakerSetDropper>,
+}
+
+// 待发送的数据不会被固定
+impl<T> Unpin for SendWaiter<'_, T> {}
+
+impl<T> Future for SendWaiter<'_, T> {
+    type Output = Result<()>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        if !this.chan.closed.get() && this.chan.is_full() {
+            let dropper = this
+                .chan
+                .send_wakers
+                .add_with_dropper(cx.waker().clone().into());
+            this._dropper.replace(dropper);
+            wait_on(WaitOn::Channel);
+            return std::task::Poll::Pending;
+        }
+
+        match this.value.take() {
+            Some(value) => std::task::Poll::Ready(this.chan.try_send(value)),
+            None => std::task::Poll::Ready(Err("value has been sent".into())),
+        }
+    }
+}
+
+impl<T> Drop for SendWaiter<'_, T> {
+    fn drop(&mut self) {
+        // 被唤醒后未发送就被drop时，将空位转交给其它等待的发送端，避免唤醒丢失
+        if self.value.is_some() && self._dropper.take().is_some() && !self.chan.is_full() {
+            wake_one(&self.chan.send_wakers);
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    chan: Rc<Chan<T>>,
+}
+
+impl<T> Receiver<T> {
+    // 通道为空时等待，全部发送端释放（或接收端关闭）且数据取完后返回None
+    pub fn recv(&mut self) -> RecvWaiter<'_, T> {
+        RecvWaiter {
+            chan: &self.chan,
+            _dropper: None,
+        }
+    }
+
+    // 通道为空时返回ErrorType::Blocked，不会再有数据时返回ErrorType::Closed
+    pub fn try_recv(&mut self) -> Result<T> {
+        match self.chan.queue.borrow_mut().pop_front() {
+            Some(value) => {
+                wake_one(&self.chan.send_wakers);
+                Ok(value)
+            }
+            None if self.chan.closed.get() || self.chan.senders.get() == 0 => {
+                Err(ErrorType::Closed.into())
+            }
+            None => Err(ErrorType::Blocked.into()),
+        }
+    }
+
+    // 关闭通道，之后的发送都会失败，已缓存的数据仍可以被取出
+    pub fn close(&mut self) {
+        self.chan.close();
+    }
+
+    // 通道中缓存的数据数量
+    pub fn len(&self) -> usize {
+        self.chan.queue.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.chan.close();
+        // 释放未被取走的数据，释放前先结束借用
+        let queue = std::mem::take(&mut *self.chan.queue.borrow_mut());
+        drop(queue);
+    }
+}
+
+pub struct RecvWaiter<'a, T> {
+    chan: &'a Chan<T>,
+    _dropper: Option<WakerSetDropper>,
+}
+
+impl<T> Future for RecvWaiter<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Some(value) = this.chan.queue.borrow_mut().pop_front() {
+            wake_one(&this.chan.send_wakers);
+            return std::task::Poll::Ready(Some(value));
+        }
+        if this.chan.closed.get() || this.chan.senders.get() == 0 {
+            return std::task::Poll::Ready(None);
+        }
+
+        let dropper = this
+            .chan
+            .recv_wakers
+            .add_with_dropper(cx.waker().clone().into());
+        this._dropper.replace(dropper);
+        wait_on(WaitOn::Channel);
+        std::task::Poll::Pending
+    }
+}
+
+pub struct UnboundedSender<T> {
+    chan: Rc<Chan<T>>,
+}
+
+impl<T> UnboundedSender<T> {
+    // 接收端关闭时返回ErrorType::Closed
+    pub fn send(&self, value: T) -> Result<()> {
+        self.chan.try_send(value)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.chan.closed.get()
+    }
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> Self {
+        self.chan.add_sender();
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for UnboundedSender<T> {
+    fn drop(&mut self) {
+        self.chan.drop_sender();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time;
+
+    use crate::{
+        select, sleep,
+        sync::mpsc::{channel, unbounded_channel},
+        time::now,
+    };
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_channel() {
+        let start = now();
+        let (tx, mut rx) = channel(2);
+        let producer = spawn!(async move {
+            let mut sent_at = Vec::new();
+            for i in 0..5 {
+                tx.send(i).await.unwrap();
+                sent_at.push(now() - start);
+            }
+            sent_at
+        });
+
+        let mut received = Vec::new();
+        while let Some(i) = rx.recv().await {
+            sleep(time::Duration::from_millis(10)).await;
+            received.push(i);
+        }
+        assert_eq!(received, [0, 1, 2, 3, 4]);
+
+        // 通道满后发送端需要等待接收端取走数据
+        let ms = time::Duration::from_millis;
+        assert_eq!(
+            producer.await.unwrap(),
+            [ms(0), ms(0), ms(0), ms(10), ms(20)]
+        );
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_channel_select() {
+        let (tx, mut rx) = channel(1);
+        tx.try_send(0).unwrap();
+        assert!(tx.try_send(1).is_err_and(|e| e.is_blocked()));
+
+        // 等待中的发送在select!中被取消
+        let mut timeout = false;
+        select! {
+            _ = tx.send(1) => {},
+            _ = sleep(time::Duration::from_millis(10)) => { timeout = true; }
+        }
+        assert!(timeout);
+
+        let tx2 = tx.clone();
+        let sender = spawn!(async move { tx2.send(2).await });
+        sleep(time::Duration::from_millis(10)).await;
+        assert_eq!(rx.recv().await, Some(0));
+        sender.await.unwrap().unwrap();
+        assert_eq!(rx.try_recv().unwrap(), 2);
+        assert!(rx.try_recv().is_err_and(|e| e.is_blocked()));
+
+        drop(tx);
+        assert_eq!(rx.recv().await, None);
+        assert!(rx.try_recv().is_err_and(|e| e.is_closed()));
+    }
+
+    #[rt_entry::test]
+    async fn test_unbounded_channel() {
+        let (tx, mut rx) = unbounded_channel();
+        for i in 0..100 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.len(), 100);
+        assert_eq!(rx.recv().await, Some(0));
+
+        rx.close();
+        assert!(tx.is_closed());
+        assert!(tx.send(100).is_err_and(|e| e.is_closed()));
+        // 关闭后仍可以取出已缓存的数据
+        assert_eq!(rx.recv().await, Some(1));
+        drop(rx);
+        assert!(tx.send(100).is_err_and(|e| e.is_closed()));
+    }
+}
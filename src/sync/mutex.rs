@@ -1,55 +1,178 @@
 use std::{
-    cell::UnsafeCell,
+    cell::{Cell, RefCell, UnsafeCell},
+    collections::BTreeMap,
+    marker::PhantomData,
     ops::{Deref, DerefMut},
-    sync::atomic::{AtomicBool, Ordering},
+    rc::Rc,
+    task::Waker,
 };
 
 use crate::{
-    runtime::add_waker,
-    task::waker_ext::{WakerSet, WakerSetDropper},
+    result::{ErrorType, Result},
+    runtime::{add_waker, dump::WaitOn, wait_on},
 };
 
-pub struct AsyncMutex<T> {
-    // 主要使用原子类型的内部可变性
-    occupied: AtomicBool,
+// 锁的状态，与数据类型无关，便于MappedMutexGuard释放锁
+struct LockState {
+    locked: Cell<bool>,
+    // 按排队序号保存的等待者，释放时将锁直接转交给最早的等待者
+    waiters: RefCell<BTreeMap<u64, Waker>>,
+    next_ticket: Cell<u64>,
+    // 已被转交锁但还未被poll的等待者
+    granted: Cell<Option<u64>>,
+}
+
+impl LockState {
+    fn try_lock(&self) -> bool {
+        // 转交时locked保持为true，因此未锁定时一定没有等待者，不存在插队
+        !self.locked.replace(true)
+    }
 
-    // 等待中的任务
-    waiting_wakers: WakerSet,
+    fn unlock(&self) {
+        let next = self.waiters.borrow_mut().pop_first();
+        match next {
+            Some((ticket, waker)) => {
+                self.granted.set(Some(ticket));
+                add_waker(waker);
+            }
+            None => self.locked.set(false),
+        }
+    }
+}
 
+/// 公平的异步互斥锁，等待者按到达顺序获取锁
+pub struct AsyncMutex<T> {
+    state: LockState,
     data: UnsafeCell<T>,
 }
 
 impl<T> AsyncMutex<T> {
     pub fn new(value: T) -> Self {
         Self {
-            occupied: AtomicBool::new(false),
-            waiting_wakers: WakerSet::default(),
+            state: LockState {
+                locked: Cell::new(false),
+                waiters: RefCell::new(BTreeMap::new()),
+                next_ticket: Cell::new(0),
+                granted: Cell::new(None),
+            },
             data: UnsafeCell::new(value),
         }
     }
 
     pub async fn lock(&self) -> AsyncMutexGuard<'_, T> {
-        let mut guard = AsyncMutexGuard::new(self);
-        (&mut guard).await;
-        guard
+        LockWaiter::new(&self.state).await;
+        AsyncMutexGuard { mtx: self }
+    }
+
+    // 锁被占用时返回ErrorType::Blocked
+    pub fn try_lock(&self) -> Result<AsyncMutexGuard<'_, T>> {
+        match self.state.try_lock() {
+            true => Ok(AsyncMutexGuard { mtx: self }),
+            false => Err(ErrorType::Blocked.into()),
+        }
+    }
+
+    // 守卫持有锁的Rc，可以移动到要求'static的任务中
+    pub async fn lock_owned(self: Rc<Self>) -> OwnedMutexGuard<T> {
+        LockWaiter::new(&self.state).await;
+        OwnedMutexGuard { mtx: self }
     }
 
-    pub(crate) unsafe fn get_mut(&self) -> &mut T {
-        unsafe { &mut *(self.data.get()) }
+    // 锁被占用时返回ErrorType::Blocked
+    pub fn try_lock_owned(self: Rc<Self>) -> Result<OwnedMutexGuard<T>> {
+        match self.state.try_lock() {
+            true => Ok(OwnedMutexGuard { mtx: self }),
+            false => Err(ErrorType::Blocked.into()),
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.state.locked.get()
+    }
+}
+
+// 在select!、timeout等场景下可能在等待中被drop，需要从队列中移除，已被转交的锁需要继续转交
+struct LockWaiter<'a> {
+    state: &'a LockState,
+    ticket: Option<u64>,
+}
+
+impl<'a> LockWaiter<'a> {
+    fn new(state: &'a LockState) -> Self {
+        Self {
+            state,
+            ticket: None,
+        }
+    }
+}
+
+impl Future for LockWaiter<'_> {
+    type Output = ();
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        match this.ticket {
+            Some(ticket) if this.state.granted.get() == Some(ticket) => {
+                this.state.granted.set(None);
+                this.ticket = None;
+                return std::task::Poll::Ready(());
+            }
+            Some(ticket) => {
+                this.state
+                    .waiters
+                    .borrow_mut()
+                    .insert(ticket, cx.waker().clone());
+            }
+            None if this.state.try_lock() => return std::task::Poll::Ready(()),
+            None => {
+                let ticket = this.state.next_ticket.get();
+                this.state.next_ticket.set(ticket + 1);
+                this.state
+                    .waiters
+                    .borrow_mut()
+                    .insert(ticket, cx.waker().clone());
+                this.ticket = Some(ticket);
+            }
+        }
+
+        wait_on(WaitOn::Mutex);
+        std::task::Poll::Pending
+    }
+}
+
+impl Drop for LockWaiter<'_> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket.take() {
+            if self.state.granted.get() == Some(ticket) {
+                self.state.granted.set(None);
+                self.state.unlock();
+            } else {
+                // 释放Waker前先结束借用
+                let waker = self.state.waiters.borrow_mut().remove(&ticket);
+                drop(waker);
+            }
+        }
     }
 }
 
 pub struct AsyncMutexGuard<'a, T> {
     mtx: &'a AsyncMutex<T>,
-    // 如果是等待锁的释放，则需要注意在部分场景下可能存在提前drop的情况（如select时），需要保证在WakerSet中的正常释放
-    _dropper: Option<WakerSetDropper>,
 }
 
 impl<'a, T> AsyncMutexGuard<'a, T> {
-    fn new(mtx: &'a AsyncMutex<T>) -> Self {
-        Self {
-            mtx,
-            _dropper: None,
+    // 将守卫映射到数据的一部分，锁在映射后的守卫释放时释放
+    pub fn map<U, F: FnOnce(&mut T) -> &mut U>(this: Self, f: F) -> MappedMutexGuard<'a, U> {
+        let state = &this.mtx.state;
+        let data = f(unsafe { &mut *this.mtx.data.get() }) as *mut U;
+        // 锁的所有权转移到映射后的守卫
+        std::mem::forget(this);
+        MappedMutexGuard {
+            state,
+            data,
+            _marker: PhantomData,
         }
     }
 }
@@ -68,52 +191,90 @@ impl<T> DerefMut for AsyncMutexGuard<'_, T> {
     }
 }
 
-impl<T> Future for AsyncMutexGuard<'_, T> {
-    type Output = ();
+impl<T> Drop for AsyncMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mtx.state.unlock();
+    }
+}
 
-    fn poll(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Self::Output> {
-        if self
-            .mtx
-            .occupied
-            .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
-            .is_err()
-        {
-            let dropper = self
-                .mtx
-                .waiting_wakers
-                .add_with_dropper(cx.waker().clone().into());
-            self.get_mut()._dropper.replace(dropper);
-            std::task::Poll::Pending
-        } else {
-            // 抢占成功
-            std::task::Poll::Ready(())
-        }
+pub struct OwnedMutexGuard<T> {
+    mtx: Rc<AsyncMutex<T>>,
+}
+
+impl<T> OwnedMutexGuard<T> {
+    pub fn mutex(&self) -> &Rc<AsyncMutex<T>> {
+        &self.mtx
     }
 }
 
-impl<T> Drop for AsyncMutexGuard<'_, T> {
+impl<T> Deref for OwnedMutexGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mtx.data.get() }
+    }
+}
+
+impl<T> DerefMut for OwnedMutexGuard<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.mtx.data.get() }
+    }
+}
+
+impl<T> Drop for OwnedMutexGuard<T> {
     fn drop(&mut self) {
-        // 在进行锁释放的场景下，需要重新唤醒一个等待的任务
-        if self
-            .mtx
-            .occupied
-            .compare_exchange(true, false, Ordering::Relaxed, Ordering::Relaxed)
-            .is_ok()
-            && let Some(waker_ext) = self.mtx.waiting_wakers.pop()
-        {
-            add_waker(waker_ext.into());
+        self.mtx.state.unlock();
+    }
+}
+
+pub struct MappedMutexGuard<'a, U> {
+    state: &'a LockState,
+    data: *mut U,
+    _marker: PhantomData<&'a mut U>,
+}
+
+impl<'a, U> MappedMutexGuard<'a, U> {
+    pub fn map<V, F: FnOnce(&mut U) -> &mut V>(this: Self, f: F) -> MappedMutexGuard<'a, V> {
+        let state = this.state;
+        let data = f(unsafe { &mut *this.data }) as *mut V;
+        std::mem::forget(this);
+        MappedMutexGuard {
+            state,
+            data,
+            _marker: PhantomData,
         }
     }
 }
 
+impl<U> Deref for MappedMutexGuard<'_, U> {
+    type Target = U;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.data }
+    }
+}
+
+impl<U> DerefMut for MappedMutexGuard<'_, U> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<U> Drop for MappedMutexGuard<'_, U> {
+    fn drop(&mut self) {
+        self.state.unlock();
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{ops::Deref, rc::Rc, time};
+    use std::{cell::RefCell, ops::Deref, rc::Rc, time};
 
-    use crate::{sleep, sync::mutex::AsyncMutex};
+    use crate::{
+        sleep,
+        sync::mutex::{AsyncMutex, AsyncMutexGuard},
+        timeout,
+    };
 
     #[rt_entry::test]
     async fn test_mtx() {
@@ -152,4 +313,84 @@ mod tests {
         spawn!(inner(mtx2.clone(), mtx1.clone()));
         // spawn!(inner(mtx1.clone(), mtx2.clone()));
     }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_mtx_fifo() {
+        let mtx = Rc::new(AsyncMutex::new(Vec::new()));
+        let guard = mtx.lock().await;
+
+        // 按到达顺序排队，lock_owned的守卫可以移动到新任务中
+        let mut handles = Vec::new();
+        for i in 0..5 {
+            let mtx = mtx.clone();
+            handles.push(spawn!(async move {
+                let mut guard = mtx.lock_owned().await;
+                guard.push(i);
+                sleep(time::Duration::from_millis(1)).await;
+            }));
+            sleep(time::Duration::from_millis(1)).await;
+        }
+
+        // 释放后锁被直接转交给等待者，不会被后来者抢占
+        drop(guard);
+        assert!(mtx.is_locked());
+        assert!(mtx.try_lock().is_err_and(|e| e.is_blocked()));
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(*mtx.try_lock().unwrap(), [0, 1, 2, 3, 4]);
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_mtx_cancel() {
+        let mtx = Rc::new(AsyncMutex::new(0));
+        let guard = mtx.lock().await;
+
+        // 超时的等待者从队列中移除
+        assert!(
+            timeout(time::Duration::from_millis(10), mtx.lock())
+                .await
+                .is_err_and(|e| e.is_timeout())
+        );
+
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let (m, o) = (mtx.clone(), order.clone());
+        let cancelled = spawn!(async move {
+            let _guard = m.lock().await;
+            o.borrow_mut().push("cancelled");
+        });
+        sleep(time::Duration::from_millis(1)).await;
+        let (m, o) = (mtx.clone(), order.clone());
+        let waiter = spawn!(async move {
+            *m.lock().await += 1;
+            o.borrow_mut().push("waiter");
+        });
+        sleep(time::Duration::from_millis(1)).await;
+        let (m, o) = (mtx.clone(), order.clone());
+        let last = spawn!(async move {
+            *m.lock().await += 1;
+            o.borrow_mut().push("last");
+        });
+        sleep(time::Duration::from_millis(1)).await;
+
+        // 已被转交锁的等待者在获取前被取消，锁继续转交给下一个等待者
+        drop(guard);
+        cancelled.abort_handle().abort();
+        waiter.await.unwrap();
+        last.await.unwrap();
+        assert_eq!(*order.borrow(), ["waiter", "last"]);
+        assert_eq!(*mtx.lock().await, 2);
+    }
+
+    #[rt_entry::test]
+    async fn test_mapped_guard() {
+        let mtx = AsyncMutex::new((0, String::new()));
+        {
+            let mut name = AsyncMutexGuard::map(mtx.lock().await, |(_, name)| name);
+            name.push_str("mini");
+            assert!(mtx.is_locked());
+        }
+        assert!(!mtx.is_locked());
+        assert_eq!(mtx.lock().await.1, "mini");
+    }
 }
@@ -1,7 +1,7 @@
 use std::{cell::RefCell, sync::Once};
 
 use crate::{
-    runtime::add_waker,
+    runtime::{add_waker, dump::WaitOn, wait_on},
     task::{
         TaskAttr,
         waker_ext::{WakerSet, WakerSetDropper},
@@ -69,6 +69,7 @@ impl<'a> Future for NotifierWaiter<'a> {
         let tid = unsafe { TaskAttr::from_raw_data(cx.waker().data()) }.get_tid();
         // notifier在进行通知的时候会移除相应的唤醒器
         if self.notifier.waiting_wakers.contains(tid) {
+            wait_on(WaitOn::Notifier);
             std::task::Poll::Pending
         } else {
             std::task::Poll::Ready(())
@@ -91,9 +92,9 @@ mod tests {
             log::info!("waiter to run - {}", num);
         }
 
-        for i in 0..10 {
-            spawn!(inner(notifier.clone(), i));
-        }
+        let handles = (0..10)
+            .map(|i| spawn!(inner(notifier.clone(), i)))
+            .collect::<Vec<_>>();
 
         for _ in 0..5 {
             sleep(time::Duration::from_millis(200)).await;
@@ -102,6 +103,9 @@ mod tests {
 
         sleep(time::Duration::from_millis(300)).await;
         notifier.notify_all();
+        for handle in handles {
+            handle.await.unwrap();
+        }
     }
 
     #[rt_entry::test]
@@ -111,7 +115,7 @@ mod tests {
         let nb = Rc::new(Notifier::new());
         let nb_c = nb.clone();
 
-        spawn!(async move {
+        let b = spawn!(async move {
             for _ in 0..10 {
                 na.wait().await;
                 log::info!("B");
@@ -119,12 +123,15 @@ mod tests {
             }
         });
 
-        spawn!(async move {
+        let a = spawn!(async move {
             for _ in 0..10 {
                 na_c.notify_one();
                 log::info!("A");
                 nb_c.wait().await;
             }
         });
+
+        a.await.unwrap();
+        b.await.unwrap();
     }
 }
@@ -0,0 +1,205 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use crate::{
+    result::{ErrorType, Result},
+    runtime::{add_waker, dump::WaitOn, wait_on},
+    task::waker_ext::{WakerSet, WakerSetDropper},
+};
+
+struct Inner<T> {
+    value: RefCell<Option<T>>,
+    // 发送端已发送或被释放
+    sender_done: Cell<bool>,
+    // 接收端被关闭或释放
+    receiver_closed: Cell<bool>,
+    // 等待数据的接收端
+    recv_wakers: WakerSet,
+    // 等待接收端关闭的发送端
+    closed_wakers: WakerSet,
+}
+
+impl<T> Inner<T> {
+    fn finish_sender(&self) {
+        self.sender_done.set(true);
+        for waker_ext in self.recv_wakers.drain() {
+            add_waker(waker_ext.into());
+        }
+    }
+
+    fn close_receiver(&self) {
+        self.receiver_closed.set(true);
+        for waker_ext in self.closed_wakers.drain() {
+            add_waker(waker_ext.into());
+        }
+    }
+}
+
+/// 一次性通道，用于任务之间的请求与响应
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Rc::new(Inner {
+        value: RefCell::new(None),
+        sender_done: Cell::new(false),
+        receiver_closed: Cell::new(false),
+        recv_wakers: WakerSet::default(),
+        closed_wakers: WakerSet::default(),
+    });
+    (
+        Sender {
+            inner: inner.clone(),
+        },
+        Receiver {
+            inner,
+            _dropper: None,
+        },
+    )
+}
+
+pub struct Sender<T> {
+    inner: Rc<Inner<T>>,
+}
+
+impl<T> Sender<T> {
+    // 接收端已关闭时返回ErrorType::Closed
+    pub fn send(self, value: T) -> Result<()> {
+        if self.inner.receiver_closed.get() {
+            return Err(ErrorType::Closed.into());
+        }
+        self.inner.value.borrow_mut().replace(value);
+        Ok(())
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.receiver_closed.get()
+    }
+
+    // 等待接收端关闭，可用于提前放弃不再需要的响应
+    pub fn closed(&self) -> ClosedWaiter<'_, T> {
+        ClosedWaiter {
+            inner: &self.inner,
+            _dropper: None,
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        // send消费了Sender，因此发送完成与未发送就被释放都在这里通知接收端
+        self.inner.finish_sender();
+    }
+}
+
+pub struct ClosedWaiter<'a, T> {
+    inner: &'a Inner<T>,
+    _dropper: Option<WakerSetDropper>,
+}
+
+impl<T> Future for ClosedWaiter<'_, T> {
+    type Output = ();
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        if self.inner.receiver_closed.get() {
+            return std::task::Poll::Ready(());
+        }
+
+        let dropper = self
+            .inner
+            .closed_wakers
+            .add_with_dropper(cx.waker().clone().into());
+        self.get_mut()._dropper.replace(dropper);
+        wait_on(WaitOn::Channel);
+        std::task::Poll::Pending
+    }
+}
+
+/// 可以直接await，发送端未发送就被释放时返回ErrorType::Closed
+pub struct Receiver<T> {
+    inner: Rc<Inner<T>>,
+    _dropper: Option<WakerSetDropper>,
+}
+
+impl<T> Receiver<T> {
+    // 尚未发送时返回ErrorType::Blocked
+    pub fn try_recv(&mut self) -> Result<T> {
+        if let Some(value) = self.inner.value.borrow_mut().take() {
+            return Ok(value);
+        }
+        match self.inner.sender_done.get() {
+            true => Err(ErrorType::Closed.into()),
+            false => Err(ErrorType::Blocked.into()),
+        }
+    }
+
+    // 关闭后发送端的send会失败，已发送的数据仍可以取出
+    pub fn close(&mut self) {
+        self.inner.close_receiver();
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        match this.try_recv() {
+            Err(e) if e.is_blocked() => {
+                let dropper = this
+                    .inner
+                    .recv_wakers
+                    .add_with_dropper(cx.waker().clone().into());
+                this._dropper.replace(dropper);
+                wait_on(WaitOn::Channel);
+                std::task::Poll::Pending
+            }
+            result => std::task::Poll::Ready(result),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.inner.close_receiver();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time;
+
+    use crate::{sleep, sync::oneshot::channel};
+
+    #[rt_entry::test]
+    async fn test_oneshot() {
+        let (tx, rx) = channel();
+        spawn!(async move {
+            sleep(time::Duration::from_millis(10)).await;
+            tx.send("pong").unwrap();
+        })
+        .detach();
+        assert_eq!(rx.await.unwrap(), "pong");
+
+        // 发送端未发送就被释放
+        let (tx, mut rx) = channel::<()>();
+        assert!(rx.try_recv().is_err_and(|e| e.is_blocked()));
+        drop(tx);
+        assert!(rx.await.is_err_and(|e| e.is_closed()));
+
+        // 接收端释放后发送端可以感知
+        let (tx, rx) = channel::<()>();
+        let waiter = spawn!(async move {
+            tx.closed().await;
+            tx.send(()).is_err()
+        });
+        sleep(time::Duration::from_millis(10)).await;
+        drop(rx);
+        assert!(waiter.await.unwrap());
+    }
+}
@@ -0,0 +1,589 @@
+use std::{
+    cell::{Cell, UnsafeCell},
+    ops::{Deref, DerefMut},
+};
+
+use crate::{
+    result::{ErrorType, Result},
+    runtime::{add_waker, dump::WaitOn, wait_on},
+    task::waker_ext::{WakerSet, WakerSetDropper},
+};
+
+/*
+ * 读写锁，读多写少的场景下读者之间不会互相阻塞
+ * 写者优先：有写者等待时新的读者需要等待，避免写者饥饿
+ * 写锁释放时唤醒此前已在等待的全部读者，这些读者不再让步于写者，避免读者饥饿
+ */
+pub struct AsyncRwLock<T> {
+    // 持有读锁的数量，可升级读锁同样计入
+    readers: Cell<usize>,
+    writer: Cell<bool>,
+    // 同一时间只能有一个可升级读锁
+    upgradable: Cell<bool>,
+    // 等待中的写者数量，等待升级的可升级读锁同样计入
+    waiting_writers: Cell<usize>,
+    // 写锁释放且有读者等待时加一
+    read_epoch: Cell<u64>,
+
+    read_wakers: WakerSet,
+    upgradable_wakers: WakerSet,
+    write_wakers: WakerSet,
+    // 等待其余读者释放的可升级读锁
+    upgrade_wakers: WakerSet,
+
+    data: UnsafeCell<T>,
+}
+
+impl<T> AsyncRwLock<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            readers: Cell::new(0),
+            writer: Cell::new(false),
+            upgradable: Cell::new(false),
+            waiting_writers: Cell::new(0),
+            read_epoch: Cell::new(0),
+            read_wakers: WakerSet::default(),
+            upgradable_wakers: WakerSet::default(),
+            write_wakers: WakerSet::default(),
+            upgrade_wakers: WakerSet::default(),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn read(&self) -> ReadWaiter<'_, T> {
+        ReadWaiter {
+            lock: self,
+            epoch: None,
+            _dropper: None,
+        }
+    }
+
+    // 无法立即获取时返回ErrorType::Blocked
+    pub fn try_read(&self) -> Result<AsyncRwLockReadGuard<'_, T>> {
+        match self.can_read(None) {
+            true => Ok(self.acquire_read()),
+            false => Err(ErrorType::Blocked.into()),
+        }
+    }
+
+    pub fn write(&self) -> WriteWaiter<'_, T> {
+        WriteWaiter {
+            lock: self,
+            waiting: false,
+            _dropper: None,
+        }
+    }
+
+    // 无法立即获取时返回ErrorType::Blocked
+    pub fn try_write(&self) -> Result<AsyncRwLockWriteGuard<'_, T>> {
+        match self.can_write() {
+            true => Ok(self.acquire_write()),
+            false => Err(ErrorType::Blocked.into()),
+        }
+    }
+
+    // 可升级读锁与普通读锁共存，但与写锁及其它可升级读锁互斥
+    pub fn upgradable_read(&self) -> UpgradableWaiter<'_, T> {
+        UpgradableWaiter {
+            lock: self,
+            epoch: None,
+            _dropper: None,
+        }
+    }
+
+    // 无法立即获取时返回ErrorType::Blocked
+    pub fn try_upgradable_read(&self) -> Result<AsyncRwLockUpgradableGuard<'_, T>> {
+        match self.can_read(None) && !self.upgradable.get() {
+            true => Ok(self.acquire_upgradable()),
+            false => Err(ErrorType::Blocked.into()),
+        }
+    }
+
+    pub(crate) unsafe fn get_mut(&self) -> &mut T {
+        unsafe { &mut *(self.data.get()) }
+    }
+
+    fn can_read(&self, epoch: Option<u64>) -> bool {
+        !self.writer.get()
+            && (self.waiting_writers.get() == 0
+                || epoch.is_some_and(|epoch| epoch < self.read_epoch.get()))
+    }
+
+    fn can_write(&self) -> bool {
+        !self.writer.get() && self.readers.get() == 0
+    }
+
+    fn acquire_read(&self) -> AsyncRwLockReadGuard<'_, T> {
+        self.readers.set(self.readers.get() + 1);
+        AsyncRwLockReadGuard { lock: self }
+    }
+
+    fn acquire_write(&self) -> AsyncRwLockWriteGuard<'_, T> {
+        self.writer.set(true);
+        AsyncRwLockWriteGuard { lock: self }
+    }
+
+    fn acquire_upgradable(&self) -> AsyncRwLockUpgradableGuard<'_, T> {
+        self.readers.set(self.readers.get() + 1);
+        self.upgradable.set(true);
+        AsyncRwLockUpgradableGuard { lock: self }
+    }
+
+    // 读者减少后唤醒可以继续的写者或等待升级的可升级读锁
+    fn notify_writer(&self) {
+        if self.writer.get() {
+            return;
+        }
+        match self.readers.get() {
+            0 => wake_one(&self.write_wakers),
+            1 if self.upgradable.get() => wake_one(&self.upgrade_wakers),
+            _ => {}
+        }
+    }
+
+    // 等待的写者全部离开后，让步于写者的读者可以继续
+    fn leave_writer(&self) {
+        self.waiting_writers.set(self.waiting_writers.get() - 1);
+        if self.waiting_writers.get() == 0 && !self.writer.get() {
+            wake_all(&self.read_wakers);
+            wake_one(&self.upgradable_wakers);
+        }
+    }
+
+    fn release_read(&self) {
+        self.readers.set(self.readers.get() - 1);
+        self.notify_writer();
+    }
+
+    fn release_upgradable(&self) {
+        self.upgradable.set(false);
+        wake_one(&self.upgradable_wakers);
+        self.release_read();
+    }
+
+    fn release_write(&self) {
+        self.writer.set(false);
+        let mut readers = self.read_wakers.drain();
+        readers.extend(self.upgradable_wakers.pop());
+        if readers.is_empty() {
+            wake_one(&self.write_wakers);
+            return;
+        }
+
+        // 优先让已在等待的读者继续，之后的写者在这批读者释放后被唤醒
+        self.read_epoch.set(self.read_epoch.get() + 1);
+        for waker_ext in readers {
+            add_waker(waker_ext.into());
+        }
+    }
+}
+
+fn wake_one(wakers: &WakerSet) {
+    if let Some(waker_ext) = wakers.pop() {
+        add_waker(waker_ext.into());
+    }
+}
+
+fn wake_all(wakers: &WakerSet) {
+    for waker_ext in wakers.drain() {
+        add_waker(waker_ext.into());
+    }
+}
+
+pub struct ReadWaiter<'a, T> {
+    lock: &'a AsyncRwLock<T>,
+    // 开始等待时的read_epoch
+    epoch: Option<u64>,
+    // 在select!等场景下可能在等待中被drop，需要保证在WakerSet中的正常释放
+    _dropper: Option<WakerSetDropper>,
+}
+
+impl<'a, T> Future for ReadWaiter<'a, T> {
+    type Output = AsyncRwLockReadGuard<'a, T>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        if this.lock.can_read(this.epoch) {
+            this._dropper.take();
+            return std::task::Poll::Ready(this.lock.acquire_read());
+        }
+
+        this.epoch.get_or_insert(this.lock.read_epoch.get());
+        let dropper = this
+            .lock
+            .read_wakers
+            .add_with_dropper(cx.waker().clone().into());
+        this._dropper.replace(dropper);
+        wait_on(WaitOn::RwLock);
+        std::task::Poll::Pending
+    }
+}
+
+impl<T> Drop for ReadWaiter<'_, T> {
+    fn drop(&mut self) {
+        // 被唤醒后未获取就被drop时，写者可能在等待这批读者释放，需要重新检查并唤醒
+        if self._dropper.take().is_some() {
+            self.lock.notify_writer();
+            if self.lock.can_read(None) && !self.lock.upgradable.get() {
+                wake_one(&self.lock.upgradable_wakers);
+            }
+        }
+    }
+}
+
+pub struct UpgradableWaiter<'a, T> {
+    lock: &'a AsyncRwLock<T>,
+    epoch: Option<u64>,
+    _dropper: Option<WakerSetDropper>,
+}
+
+impl<'a, T> Future for UpgradableWaiter<'a, T> {
+    type Output = AsyncRwLockUpgradableGuard<'a, T>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        if this.lock.can_read(this.epoch) && !this.lock.upgradable.get() {
+            this._dropper.take();
+            return std::task::Poll::Ready(this.lock.acquire_upgradable());
+        }
+
+        this.epoch.get_or_insert(this.lock.read_epoch.get());
+        let dropper = this
+            .lock
+            .upgradable_wakers
+            .add_with_dropper(cx.waker().clone().into());
+        this._dropper.replace(dropper);
+        wait_on(WaitOn::RwLock);
+        std::task::Poll::Pending
+    }
+}
+
+impl<T> Drop for UpgradableWaiter<'_, T> {
+    fn drop(&mut self) {
+        // 被唤醒后未获取就被drop时，转交给其它等待的可升级读锁，避免唤醒丢失
+        if self._dropper.take().is_some() && !self.lock.upgradable.get() {
+            wake_one(&self.lock.upgradable_wakers);
+        }
+    }
+}
+
+pub struct WriteWaiter<'a, T> {
+    lock: &'a AsyncRwLock<T>,
+    // 是否已计入waiting_writers
+    waiting: bool,
+    _dropper: Option<WakerSetDropper>,
+}
+
+impl<'a, T> Future for WriteWaiter<'a, T> {
+    type Output = AsyncRwLockWriteGuard<'a, T>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        if this.lock.can_write() {
+            this._dropper.take();
+            let guard = this.lock.acquire_write();
+            if std::mem::take(&mut this.waiting) {
+                this.lock.leave_writer();
+            }
+            return std::task::Poll::Ready(guard);
+        }
+
+        if !this.waiting {
+            this.waiting = true;
+            this.lock
+                .waiting_writers
+                .set(this.lock.waiting_writers.get() + 1);
+        }
+        let dropper = this
+            .lock
+            .write_wakers
+            .add_with_dropper(cx.waker().clone().into());
+        this._dropper.replace(dropper);
+        wait_on(WaitOn::RwLock);
+        std::task::Poll::Pending
+    }
+}
+
+impl<T> Drop for WriteWaiter<'_, T> {
+    fn drop(&mut self) {
+        if self.waiting {
+            self._dropper.take();
+            self.lock.leave_writer();
+            // 被唤醒后未获取就被drop时，转交给其它等待的写者，避免唤醒丢失
+            self.lock.notify_writer();
+        }
+    }
+}
+
+pub struct AsyncRwLockReadGuard<'a, T> {
+    lock: &'a AsyncRwLock<T>,
+}
+
+impl<T> Deref for AsyncRwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for AsyncRwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release_read();
+    }
+}
+
+pub struct AsyncRwLockWriteGuard<'a, T> {
+    lock: &'a AsyncRwLock<T>,
+}
+
+impl<T> Deref for AsyncRwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for AsyncRwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for AsyncRwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release_write();
+    }
+}
+
+pub struct AsyncRwLockUpgradableGuard<'a, T> {
+    lock: &'a AsyncRwLock<T>,
+}
+
+impl<'a, T> AsyncRwLockUpgradableGuard<'a, T> {
+    // 等待其余读者释放后升级为写锁，等待期间新的读者需要等待
+    pub fn upgrade(self) -> UpgradeWaiter<'a, T> {
+        UpgradeWaiter {
+            guard: Some(self),
+            waiting: false,
+            _dropper: None,
+        }
+    }
+
+    // 没有其它读者时立即升级为写锁，否则原样返回
+    pub fn try_upgrade(self) -> std::result::Result<AsyncRwLockWriteGuard<'a, T>, Self> {
+        match self.lock.readers.get() {
+            1 => Ok(self.into_write()),
+            _ => Err(self),
+        }
+    }
+
+    fn into_write(self) -> AsyncRwLockWriteGuard<'a, T> {
+        let lock = self.lock;
+        // 所有权转移到写锁，不再执行可升级读锁的释放
+        std::mem::forget(self);
+        lock.readers.set(lock.readers.get() - 1);
+        lock.upgradable.set(false);
+        lock.acquire_write()
+    }
+}
+
+impl<T> Deref for AsyncRwLockUpgradableGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for AsyncRwLockUpgradableGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release_upgradable();
+    }
+}
+
+pub struct UpgradeWaiter<'a, T> {
+    // 升级完成后置空，未完成时drop会释放可升级读锁
+    guard: Option<AsyncRwLockUpgradableGuard<'a, T>>,
+    waiting: bool,
+    _dropper: Option<WakerSetDropper>,
+}
+
+impl<'a, T> Future for UpgradeWaiter<'a, T> {
+    type Output = AsyncRwLockWriteGuard<'a, T>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        let Some(guard) = this.guard.take() else {
+            panic!("upgrade polled after completion");
+        };
+        let lock = guard.lock;
+        let guard = match guard.try_upgrade() {
+            Ok(write_guard) => {
+                this._dropper.take();
+                if std::mem::take(&mut this.waiting) {
+                    lock.leave_writer();
+                }
+                return std::task::Poll::Ready(write_guard);
+            }
+            Err(guard) => guard,
+        };
+        this.guard.replace(guard);
+
+        if !this.waiting {
+            this.waiting = true;
+            lock.waiting_writers.set(lock.waiting_writers.get() + 1);
+        }
+        let dropper = lock
+            .upgrade_wakers
+            .add_with_dropper(cx.waker().clone().into());
+        this._dropper.replace(dropper);
+        wait_on(WaitOn::RwLock);
+        std::task::Poll::Pending
+    }
+}
+
+impl<T> Drop for UpgradeWaiter<'_, T> {
+    fn drop(&mut self) {
+        if self.waiting
+            && let Some(guard) = self.guard.as_ref()
+        {
+            self._dropper.take();
+            guard.lock.leave_writer();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, time};
+
+    use crate::{select, sleep, sync::rwlock::AsyncRwLock, time::now, timeout};
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_rwlock() {
+        let start = now();
+        let lock = Rc::new(AsyncRwLock::new(0));
+        let events = Rc::new(RefCell::new(Vec::new()));
+
+        // 读者之间不会互相阻塞
+        let mut handles = Vec::new();
+        for i in 0..3 {
+            let (lock, events) = (lock.clone(), events.clone());
+            handles.push(spawn!(async move {
+                let guard = lock.read().await;
+                sleep(time::Duration::from_millis(10)).await;
+                events.borrow_mut().push(format!("read{}:{}", i, *guard));
+            }));
+        }
+        sleep(time::Duration::from_millis(1)).await;
+
+        // 写者等待读者释放，之后到达的读者让步于写者
+        let writer = {
+            let (lock, events) = (lock.clone(), events.clone());
+            spawn!(async move {
+                let mut guard = lock.write().await;
+                *guard += 1;
+                events.borrow_mut().push("write".to_owned());
+            })
+        };
+        sleep(time::Duration::from_millis(1)).await;
+        assert!(lock.try_read().is_err_and(|e| e.is_blocked()));
+        let late = lock.read().await;
+        assert_eq!(*late, 1);
+        assert_eq!(now() - start, time::Duration::from_millis(10));
+        drop(late);
+
+        writer.await.unwrap();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        // 同一时刻到期的读者顺序不确定，写者一定在全部读者之后
+        let mut events = events.take();
+        assert_eq!(events.pop().unwrap(), "write");
+        events.sort();
+        assert_eq!(events, ["read0:0", "read1:0", "read2:0"]);
+        assert!(lock.try_write().is_ok());
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_rwlock_upgrade() {
+        let lock = Rc::new(AsyncRwLock::new(Vec::new()));
+        let upgradable = lock.upgradable_read().await;
+        // 可升级读锁与读锁共存，但与其它可升级读锁互斥
+        let reader = lock.read().await;
+        assert!(lock.try_upgradable_read().is_err());
+
+        let lock2 = lock.clone();
+        let waiter = spawn!(async move {
+            sleep(time::Duration::from_millis(10)).await;
+            drop(reader);
+            lock2.read().await.len()
+        });
+
+        let upgradable = match upgradable.try_upgrade() {
+            Ok(_) => panic!("upgrade with other readers"),
+            Err(upgradable) => upgradable,
+        };
+        let start = now();
+        let mut guard = upgradable.upgrade().await;
+        assert_eq!(now() - start, time::Duration::from_millis(10));
+        guard.push(1);
+        drop(guard);
+        assert_eq!(waiter.await.unwrap(), 1);
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_rwlock_select() {
+        let lock = AsyncRwLock::new(0);
+        let reader = lock.read().await;
+
+        // 等待中的写者被取消后，让步于它的读者可以继续
+        let mut timeout = false;
+        select! {
+            _ = lock.write() => {},
+            _ = sleep(time::Duration::from_millis(10)) => { timeout = true; }
+        }
+        assert!(timeout);
+        assert!(lock.try_read().is_ok());
+
+        drop(reader);
+        *lock.write().await += 1;
+        assert_eq!(*lock.read().await, 1);
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_rwlock_abort_woken_reader() {
+        let lock = Rc::new(AsyncRwLock::new(0));
+        let w1 = lock.write().await;
+
+        let l = lock.clone();
+        let reader = spawn!(async move { *l.read().await });
+        sleep(time::Duration::from_millis(1)).await;
+        let l = lock.clone();
+        let w2 = spawn!(async move { *l.write().await += 1 });
+        sleep(time::Duration::from_millis(1)).await;
+
+        // 写锁释放时只唤醒了等待的读者，读者在获取前被取消后写者仍需被唤醒
+        drop(w1);
+        reader.abort();
+        timeout(time::Duration::from_secs(5), w2)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*lock.read().await, 1);
+    }
+}
@@ -1,114 +1,396 @@
 use std::{
-    cell::RefCell,
-    sync::{
-        Once,
-        atomic::{AtomicUsize, Ordering},
-    },
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashSet},
+    rc::Rc,
+    task::Waker,
 };
 
 use crate::{
-    runtime::add_waker,
-    task::waker_ext::{WakerSet, WakerSetDropper},
+    result::{ErrorType, Result},
+    runtime::{add_waker, dump::WaitOn, wait_on},
 };
 
-pub struct AsyncSemophore {
-    capacity: usize,
-
-    count: AtomicUsize,
+struct Waiter {
+    permits: usize,
+    waker: Waker,
+}
 
-    waiting_wakers: WakerSet,
+/*
+ * 公平的异步信号量，等待者按到达顺序获取许可
+ * 队首的等待者许可不足时，后面的等待者即使需要的许可更少也需要等待，避免获取多个许可的等待者饥饿
+ */
+pub struct AsyncSemophore {
+    // 当前可用的许可数量
+    permits: Cell<usize>,
+    // 许可的总数，包括已被获取的许可
+    total: Cell<usize>,
+    closed: Cell<bool>,
+    // 按排队序号保存的等待者
+    waiters: RefCell<BTreeMap<u64, Waiter>>,
+    next_ticket: Cell<u64>,
+    // 已被分配许可但还未被poll的等待者
+    granted: RefCell<HashSet<u64>>,
 }
 
 impl AsyncSemophore {
-    pub fn new(capacity: usize) -> Self {
+    pub fn new(permits: usize) -> Self {
         Self {
-            capacity,
-            count: AtomicUsize::new(0),
-            waiting_wakers: WakerSet::default(),
+            permits: Cell::new(permits),
+            total: Cell::new(permits),
+            closed: Cell::new(false),
+            waiters: RefCell::new(BTreeMap::new()),
+            next_ticket: Cell::new(0),
+            granted: RefCell::new(HashSet::new()),
         }
     }
 
-    pub async fn aquire(&self) -> AsyncSemophoreGuard<'_> {
-        let mut guard = AsyncSemophoreGuard::new(self);
-        (&mut guard).await;
-        guard
+    // 信号量被关闭时返回ErrorType::Closed
+    pub async fn acquire(&self) -> Result<AsyncSemophoreGuard<'_>> {
+        self.acquire_many(1).await
+    }
+
+    // 兼容原有的拼写
+    pub async fn aquire(&self) -> Result<AsyncSemophoreGuard<'_>> {
+        self.acquire().await
+    }
+
+    // 需要的许可超过总数时返回错误，避免一直排在队首阻塞之后的等待者
+    pub async fn acquire_many(&self, permits: usize) -> Result<AsyncSemophoreGuard<'_>> {
+        AcquireWaiter::new(self, permits).await?;
+        Ok(AsyncSemophoreGuard {
+            semophore: self,
+            permits,
+        })
+    }
+
+    // 许可不足（或有其它等待者在排队）时返回ErrorType::Blocked，被关闭时返回ErrorType::Closed
+    pub fn try_acquire(&self) -> Result<AsyncSemophoreGuard<'_>> {
+        self.try_acquire_many(1)
+    }
+
+    pub fn try_acquire_many(&self, permits: usize) -> Result<AsyncSemophoreGuard<'_>> {
+        self.try_take(permits)?;
+        Ok(AsyncSemophoreGuard {
+            semophore: self,
+            permits,
+        })
+    }
+
+    // 许可持有信号量的Rc，可以移动到要求'static的任务中
+    pub async fn acquire_owned(self: Rc<Self>) -> Result<OwnedSemophorePermit> {
+        self.acquire_many_owned(1).await
+    }
+
+    pub async fn acquire_many_owned(
+        self: Rc<Self>,
+        permits: usize,
+    ) -> Result<OwnedSemophorePermit> {
+        AcquireWaiter::new(&self, permits).await?;
+        Ok(OwnedSemophorePermit {
+            semophore: self,
+            permits,
+        })
+    }
+
+    pub fn try_acquire_owned(self: Rc<Self>) -> Result<OwnedSemophorePermit> {
+        self.try_acquire_many_owned(1)
+    }
+
+    pub fn try_acquire_many_owned(self: Rc<Self>, permits: usize) -> Result<OwnedSemophorePermit> {
+        self.try_take(permits)?;
+        Ok(OwnedSemophorePermit {
+            semophore: self,
+            permits,
+        })
+    }
+
+    // 增加许可，并按顺序唤醒许可已满足的等待者
+    pub fn add_permits(&self, permits: usize) {
+        self.total.set(self.total.get() + permits);
+        self.release(permits);
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.permits.get()
+    }
+
+    // 关闭后等待中的和之后的获取都会返回ErrorType::Closed，已获取的许可不受影响
+    pub fn close(&self) {
+        self.closed.set(true);
+        let waiters = std::mem::take(&mut *self.waiters.borrow_mut());
+        for (_, waiter) in waiters {
+            add_waker(waiter.waker);
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.get()
+    }
+
+    fn try_take(&self, permits: usize) -> Result<()> {
+        if self.closed.get() {
+            return Err(ErrorType::Closed.into());
+        }
+        if permits > self.total.get() {
+            return Err(format!(
+                "acquire {} permits but semophore only has {}",
+                permits,
+                self.total.get()
+            )
+            .into());
+        }
+        // 有等待者时不允许插队
+        if !self.waiters.borrow().is_empty() || self.permits.get() < permits {
+            return Err(ErrorType::Blocked.into());
+        }
+        self.permits.set(self.permits.get() - permits);
+        Ok(())
+    }
+
+    // 归还许可，并按顺序唤醒许可已满足的等待者
+    fn release(&self, permits: usize) {
+        self.permits.set(self.permits.get() + permits);
+        self.notify();
+    }
+
+    fn notify(&self) {
+        loop {
+            let mut waiters = self.waiters.borrow_mut();
+            let Some(entry) = waiters.first_entry() else {
+                return;
+            };
+            if entry.get().permits > self.permits.get() {
+                return;
+            }
+
+            let ticket = *entry.key();
+            self.permits.set(self.permits.get() - entry.get().permits);
+            let waker = entry.remove().waker;
+            drop(waiters);
+            self.granted.borrow_mut().insert(ticket);
+            add_waker(waker);
+        }
     }
 }
 
-pub struct AsyncSemophoreGuard<'a> {
+// 在select!、timeout等场景下可能在等待中被drop，需要从队列中移除，已被分配的许可需要归还
+struct AcquireWaiter<'a> {
     semophore: &'a AsyncSemophore,
-    once: Once,
-    // 是否获取到信号量：在没有获取到信号量的时候被删除不需要进行信号量的释放
-    fetched: bool,
-    _dropper: RefCell<Option<WakerSetDropper>>,
+    permits: usize,
+    ticket: Option<u64>,
 }
 
-impl<'a> AsyncSemophoreGuard<'a> {
-    fn new(semophore: &'a AsyncSemophore) -> Self {
+impl<'a> AcquireWaiter<'a> {
+    fn new(semophore: &'a AsyncSemophore, permits: usize) -> Self {
         Self {
             semophore,
-            once: Once::new(),
-            fetched: false,
-            _dropper: RefCell::new(None),
+            permits,
+            ticket: None,
         }
     }
 }
 
-impl<'a> Future for AsyncSemophoreGuard<'a> {
-    type Output = ();
+impl Future for AcquireWaiter<'_> {
+    type Output = Result<()>;
 
     fn poll(
         self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
-        // 在信号量已满的情况下，加入等待队列中
-        if self.semophore.count.load(Ordering::Relaxed) == self.semophore.capacity {
-            self.once.call_once(|| {
-                let dropper = self
-                    .semophore
-                    .waiting_wakers
-                    .add_with_dropper(cx.waker().clone().into());
-                self._dropper.borrow_mut().replace(dropper);
-            });
-            std::task::Poll::Pending
-        } else {
-            self.semophore.count.fetch_add(1, Ordering::Relaxed);
-            self.get_mut().fetched = true;
-            std::task::Poll::Ready(())
+        let this = self.get_mut();
+        let semophore = this.semophore;
+        match this.ticket {
+            Some(ticket) if semophore.granted.borrow_mut().remove(&ticket) => {
+                this.ticket = None;
+                return std::task::Poll::Ready(Ok(()));
+            }
+            _ if semophore.closed.get() => {
+                this.ticket = None;
+                return std::task::Poll::Ready(Err(ErrorType::Closed.into()));
+            }
+            Some(ticket) => {
+                if let Some(waiter) = semophore.waiters.borrow_mut().get_mut(&ticket) {
+                    waiter.waker.clone_from(cx.waker());
+                }
+            }
+            None => match semophore.try_take(this.permits) {
+                Err(e) if e.is_blocked() => {
+                    let ticket = semophore.next_ticket.get();
+                    semophore.next_ticket.set(ticket + 1);
+                    semophore.waiters.borrow_mut().insert(
+                        ticket,
+                        Waiter {
+                            permits: this.permits,
+                            waker: cx.waker().clone(),
+                        },
+                    );
+                    this.ticket = Some(ticket);
+                }
+                result => return std::task::Poll::Ready(result),
+            },
         }
+
+        wait_on(WaitOn::Semaphore);
+        std::task::Poll::Pending
     }
 }
 
-impl<'a> Drop for AsyncSemophoreGuard<'a> {
+impl Drop for AcquireWaiter<'_> {
     fn drop(&mut self) {
-        // 在获取到信号量的时候需要进行释放
-        if self.fetched {
-            self.semophore.count.fetch_sub(1, Ordering::Relaxed);
-            if let Some(waker_ext) = self.semophore.waiting_wakers.pop() {
-                add_waker(waker_ext.into());
-            }
+        let Some(ticket) = self.ticket.take() else {
+            return;
+        };
+        let semophore = self.semophore;
+        if semophore.granted.borrow_mut().remove(&ticket) {
+            semophore.release(self.permits);
+            return;
         }
+
+        let waiter = semophore.waiters.borrow_mut().remove(&ticket);
+        drop(waiter);
+        // 移除的可能是队首，后面的等待者或许已经可以获取
+        semophore.notify();
+    }
+}
+
+pub struct AsyncSemophoreGuard<'a> {
+    semophore: &'a AsyncSemophore,
+    permits: usize,
+}
+
+impl AsyncSemophoreGuard<'_> {
+    pub fn permits(&self) -> usize {
+        self.permits
+    }
+}
+
+impl Drop for AsyncSemophoreGuard<'_> {
+    fn drop(&mut self) {
+        self.semophore.release(self.permits);
+    }
+}
+
+pub struct OwnedSemophorePermit {
+    semophore: Rc<AsyncSemophore>,
+    permits: usize,
+}
+
+impl OwnedSemophorePermit {
+    pub fn permits(&self) -> usize {
+        self.permits
+    }
+
+    pub fn semophore(&self) -> &Rc<AsyncSemophore> {
+        &self.semophore
+    }
+}
+
+impl Drop for OwnedSemophorePermit {
+    fn drop(&mut self) {
+        self.semophore.release(self.permits);
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{rc::Rc, time};
+    use std::{cell::RefCell, rc::Rc, time};
 
-    use crate::{sleep, sync::semophore::AsyncSemophore};
+    use crate::{sleep, sync::semophore::AsyncSemophore, time::now, timeout};
 
-    #[rt_entry::test]
+    #[rt_entry::test(start_paused = true)]
     async fn test_semophore() {
+        let start = now();
         let semophore = Rc::new(AsyncSemophore::new(3));
 
         async fn inner(semophore: Rc<AsyncSemophore>, num: usize) {
-            let _guard = semophore.aquire().await;
+            let _guard = semophore.aquire().await.unwrap();
             log::info!("get num {}", num);
             sleep(time::Duration::from_millis(500)).await;
         }
 
-        for i in 0..10 {
-            spawn!(inner(semophore.clone(), i));
+        let handles = (0..10)
+            .map(|i| spawn!(inner(semophore.clone(), i)))
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.await.unwrap();
         }
+        // 每次最多3个并发，10个任务分4批完成
+        assert_eq!(now() - start, time::Duration::from_millis(2000));
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_semophore_fifo() {
+        let semophore = Rc::new(AsyncSemophore::new(3));
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let guard = semophore.acquire_many(2).await.unwrap();
+
+        // 队首需要3个许可，之后只需要1个许可的等待者也不能插队
+        let mut handles = Vec::new();
+        for (i, permits) in [3, 1, 1].into_iter().enumerate() {
+            let (semophore, order) = (semophore.clone(), order.clone());
+            handles.push(spawn!(async move {
+                let permit = semophore.acquire_many_owned(permits).await.unwrap();
+                order.borrow_mut().push(i);
+                sleep(time::Duration::from_millis(10)).await;
+                drop(permit);
+            }));
+            sleep(time::Duration::from_millis(1)).await;
+        }
+        assert_eq!(semophore.available_permits(), 1);
+        assert!(semophore.try_acquire().is_err_and(|e| e.is_blocked()));
+
+        drop(guard);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(*order.borrow(), [0, 1, 2]);
+        assert_eq!(semophore.available_permits(), 3);
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_semophore_close() {
+        let semophore = Rc::new(AsyncSemophore::new(1));
+        let held = semophore.acquire().await.unwrap();
+
+        // 超时的等待者从队列中移除，不影响之后释放的许可
+        assert!(
+            timeout(time::Duration::from_millis(10), semophore.acquire())
+                .await
+                .is_err_and(|e| e.is_timeout())
+        );
+        drop(held);
+        let permit = semophore.clone().try_acquire_owned().unwrap();
+
+        let s = semophore.clone();
+        let waiter = spawn!(async move { s.acquire().await.is_err_and(|e| e.is_closed()) });
+        sleep(time::Duration::from_millis(1)).await;
+
+        // 关闭时等待中的获取失败，已获取的许可不受影响
+        semophore.close();
+        assert!(waiter.await.unwrap());
+        assert!(semophore.try_acquire().is_err_and(|e| e.is_closed()));
+        assert_eq!(permit.permits(), 1);
+        drop(permit);
+        assert_eq!(semophore.available_permits(), 1);
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_semophore_exceed_total() {
+        let semophore = AsyncSemophore::new(2);
+        let guard = semophore.acquire().await.unwrap();
+
+        // 超过总数的获取直接失败，不会排在队首阻塞之后的等待者
+        assert!(semophore.acquire_many(3).await.is_err());
+        assert!(
+            semophore
+                .try_acquire_many(3)
+                .is_err_and(|e| !e.is_blocked())
+        );
+        assert_eq!(semophore.acquire().await.unwrap().permits(), 1);
+        drop(guard);
+
+        // 增加许可后总数随之增加
+        semophore.add_permits(1);
+        assert_eq!(semophore.acquire_many(3).await.unwrap().permits(), 3);
     }
 }
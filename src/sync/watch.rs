@@ -0,0 +1,222 @@
+use std::{
+    cell::{Cell, Ref, RefCell},
+    rc::Rc,
+};
+
+use crate::{
+    result::{ErrorType, Result},
+    runtime::{add_waker, dump::WaitOn, wait_on},
+    task::waker_ext::{WakerSet, WakerSetDropper},
+};
+
+struct Shared<T> {
+    value: RefCell<T>,
+    // 每次更新加一，接收端记录已看到的版本
+    version: Cell<u64>,
+    // 发送端被释放
+    closed: Cell<bool>,
+    receivers: Cell<usize>,
+    // 等待变更的接收端
+    wakers: WakerSet,
+}
+
+impl<T> Shared<T> {
+    fn wake_all(&self) {
+        for waker_ext in self.wakers.drain() {
+            add_waker(waker_ext.into());
+        }
+    }
+
+    fn new_receiver(self: &Rc<Self>, seen: u64) -> Receiver<T> {
+        self.receivers.set(self.receivers.get() + 1);
+        Receiver {
+            shared: self.clone(),
+            seen,
+        }
+    }
+}
+
+/// 只保留最新值的通道，适合广播配置等状态，接收端只关心最后一次变更
+pub fn channel<T>(init: T) -> (Sender<T>, Receiver<T>) {
+    let shared = Rc::new(Shared {
+        value: RefCell::new(init),
+        version: Cell::new(0),
+        closed: Cell::new(false),
+        receivers: Cell::new(0),
+        wakers: WakerSet::default(),
+    });
+    let receiver = shared.new_receiver(0);
+    (Sender { shared }, receiver)
+}
+
+pub struct Sender<T> {
+    shared: Rc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    // 没有接收端时返回ErrorType::Closed，值仍会被更新，之后subscribe的接收端可以看到
+    pub fn send(&self, value: T) -> Result<()> {
+        self.send_replace(value);
+        match self.shared.receivers.get() {
+            0 => Err(ErrorType::Closed.into()),
+            _ => Ok(()),
+        }
+    }
+
+    // 更新值并返回旧值
+    pub fn send_replace(&self, value: T) -> T {
+        let old = self.shared.value.replace(value);
+        self.notify();
+        old
+    }
+
+    // 原地修改值，修改后通知全部接收端
+    pub fn send_modify<F: FnOnce(&mut T)>(&self, f: F) {
+        f(&mut self.shared.value.borrow_mut());
+        self.notify();
+    }
+
+    // 不要跨越await持有返回的借用
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.shared.value.borrow()
+    }
+
+    // 新的接收端只会等待订阅之后的变更
+    pub fn subscribe(&self) -> Receiver<T> {
+        self.shared.new_receiver(self.shared.version.get())
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.shared.receivers.get()
+    }
+
+    fn notify(&self) {
+        self.shared.version.set(self.shared.version.get() + 1);
+        self.shared.wake_all();
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.shared.closed.set(true);
+        self.shared.wake_all();
+    }
+}
+
+pub struct Receiver<T> {
+    shared: Rc<Shared<T>>,
+    // 已看到的版本
+    seen: u64,
+}
+
+impl<T> Receiver<T> {
+    // 读取最新值，不会标记为已看到；不要跨越await持有返回的借用
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.shared.value.borrow()
+    }
+
+    // 读取最新值并标记为已看到
+    pub fn borrow_and_update(&mut self) -> Ref<'_, T> {
+        self.seen = self.shared.version.get();
+        self.shared.value.borrow()
+    }
+
+    pub fn has_changed(&self) -> bool {
+        self.seen != self.shared.version.get()
+    }
+
+    /*
+     * 等待值发生变更并标记为已看到，调用前已有未看到的变更时立即返回
+     * 发送端释放且没有未看到的变更时返回ErrorType::Closed
+     */
+    pub fn changed(&mut self) -> ChangedWaiter<'_, T> {
+        ChangedWaiter {
+            receiver: self,
+            _dropper: None,
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.new_receiver(self.seen)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.receivers.set(self.shared.receivers.get() - 1);
+    }
+}
+
+pub struct ChangedWaiter<'a, T> {
+    receiver: &'a mut Receiver<T>,
+    _dropper: Option<WakerSetDropper>,
+}
+
+impl<T> Future for ChangedWaiter<'_, T> {
+    type Output = Result<()>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        let shared = &this.receiver.shared;
+        if this.receiver.seen != shared.version.get() {
+            this.receiver.seen = shared.version.get();
+            return std::task::Poll::Ready(Ok(()));
+        }
+        if shared.closed.get() {
+            return std::task::Poll::Ready(Err(ErrorType::Closed.into()));
+        }
+
+        let dropper = shared.wakers.add_with_dropper(cx.waker().clone().into());
+        this._dropper.replace(dropper);
+        wait_on(WaitOn::Channel);
+        std::task::Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time;
+
+    use crate::{select, sleep, sync::watch::channel};
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_watch() {
+        let (tx, mut rx) = channel(1);
+        let mut rx2 = tx.subscribe();
+        assert!(!rx.has_changed());
+
+        let watcher = spawn!(async move {
+            let mut seen = Vec::new();
+            while rx2.changed().await.is_ok() {
+                seen.push(*rx2.borrow());
+            }
+            seen
+        });
+
+        // 连续的变更只会看到最新值
+        tx.send(2).unwrap();
+        tx.send_modify(|v| *v += 1);
+        sleep(time::Duration::from_millis(10)).await;
+        tx.send(4).unwrap();
+        assert!(rx.has_changed());
+        assert_eq!(*rx.borrow_and_update(), 4);
+        assert!(!rx.has_changed());
+
+        // 没有变更时一直等待
+        let mut timeout = false;
+        select! {
+            _ = rx.changed() => {},
+            _ = sleep(time::Duration::from_millis(10)) => { timeout = true; }
+        }
+        assert!(timeout);
+
+        drop(tx);
+        assert!(rx.changed().await.is_err_and(|e| e.is_closed()));
+        assert_eq!(watcher.await.unwrap(), [3, 4]);
+    }
+}
@@ -0,0 +1,404 @@
+use std::{
+    pin::Pin,
+    ptr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+use crate::{
+    runtime::{
+        add_waker, remote,
+        remote::{RemoteHandle, thread_mark},
+    },
+    task::TaskAttr,
+};
+
+// 同一组合器的子Waker共享的状态
+struct JoinWake {
+    // 最近一次poll组合器时的Waker
+    parent: Mutex<Option<Waker>>,
+    // 各个子Future是否被唤醒
+    woken: Vec<AtomicBool>,
+}
+
+/*
+ * 组合器中单个子Future的Waker，唤醒时只标记对应的子Future，再唤醒组合器所在的任务
+ * 与Task相同以TaskAttr开头，保证io、notifier等通过Waker::data()获取TaskAttr的逻辑仍然可用
+ * 每个子Future拥有独立的TaskAttr，其注册不会与同一任务中的其它子Future冲突
+ */
+#[repr(C)]
+struct ChildWaker {
+    attr: TaskAttr,
+    shared: Arc<JoinWake>,
+    index: usize,
+    remote: RemoteHandle,
+    thread: usize,
+}
+
+impl ChildWaker {
+    fn new_waker(attr: TaskAttr, shared: Arc<JoinWake>, index: usize) -> Waker {
+        let child = Box::new(Self {
+            attr,
+            shared,
+            index,
+            remote: remote(),
+            thread: thread_mark(),
+        });
+        unsafe { Waker::new(Box::into_raw(child) as *const (), &VTABLE) }
+    }
+
+    fn foreign(&self) -> bool {
+        self.thread != thread_mark()
+    }
+
+    fn clone(data: *const ()) -> RawWaker {
+        let child = unsafe { &*(data as *const Self) };
+        let cloned = Box::new(Self {
+            attr: if child.foreign() {
+                // 与Task相同，其它线程上按位复制，由所属线程补充引用计数
+                unsafe { ptr::read(&child.attr) }
+            } else {
+                child.attr.clone()
+            },
+            shared: child.shared.clone(),
+            index: child.index,
+            remote: child.remote.clone(),
+            thread: child.thread,
+        });
+        let cloned = Box::into_raw(cloned) as *const ();
+        if child.foreign() {
+            child.remote.push_retain(cloned, Self::retain);
+        }
+        RawWaker::new(cloned, &VTABLE)
+    }
+
+    fn retain(data: *const ()) {
+        let child = unsafe { &*(data as *const Self) };
+        std::mem::forget(child.attr.clone());
+    }
+
+    fn wake(data: *const ()) {
+        Self::wake_by_ref(data);
+        Self::drop(data);
+    }
+
+    fn wake_by_ref(data: *const ()) {
+        let child = unsafe { &*(data as *const Self) };
+        // 其它线程上不能访问TaskAttr，只做标记，由任务的Waker转交给所属线程
+        if !child.foreign() && child.attr.finished() {
+            return;
+        }
+
+        child.shared.woken[child.index].store(true, Ordering::Release);
+        let parent = child.shared.parent.lock().unwrap().clone();
+        if let Some(parent) = parent {
+            if child.foreign() {
+                parent.wake();
+            } else {
+                // 不在当前的poll中直接驱动任务，避免重入
+                add_waker(parent);
+            }
+        }
+    }
+
+    fn drop(data: *const ()) {
+        let child = unsafe { &*(data as *const Self) };
+        if child.foreign() {
+            let remote = child.remote.clone();
+            remote.push_drop(unsafe { Waker::new(data, &VTABLE) });
+            return;
+        }
+
+        child.remote.apply_retains();
+        let _ = unsafe { Box::from_raw(data as *mut Self) };
+    }
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(
+    ChildWaker::clone,
+    ChildWaker::wake,
+    ChildWaker::wake_by_ref,
+    ChildWaker::drop,
+);
+
+/// join!等组合器内部使用，为每个子Future提供独立的Waker，只重新poll被唤醒的子Future
+#[doc(hidden)]
+pub struct JoinWakers {
+    len: usize,
+    // 第一次poll时创建，保证组合器可以在运行时之外构造
+    inner: Option<(Arc<JoinWake>, Vec<Waker>)>,
+}
+
+impl JoinWakers {
+    pub fn new(len: usize) -> Self {
+        Self { len, inner: None }
+    }
+
+    // 每次poll组合器时调用，记录最新的Waker
+    pub fn register(&mut self, cx: &Context<'_>) {
+        let (shared, _) = self.inner.get_or_insert_with(|| {
+            let shared = Arc::new(JoinWake {
+                parent: Mutex::new(None),
+                // 第一次poll时需要poll全部子Future
+                woken: (0..self.len).map(|_| AtomicBool::new(true)).collect(),
+            });
+            let wakers = (0..self.len)
+                .map(|index| ChildWaker::new_waker(TaskAttr::new(), shared.clone(), index))
+                .collect();
+            (shared, wakers)
+        });
+        shared.parent.lock().unwrap().replace(cx.waker().clone());
+    }
+
+    // 子Future已就绪时返回true，只有被唤醒过的子Future才会被poll
+    pub fn poll_child<F: Future>(&self, index: usize, fut: &mut MaybeDone<F>) -> bool {
+        let (shared, wakers) = self.inner.as_ref().expect("JoinWakers is not registered");
+        if fut.is_done() || !shared.woken[index].swap(false, Ordering::AcqRel) {
+            return fut.is_done();
+        }
+        fut.poll(&mut Context::from_waker(&wakers[index]))
+    }
+}
+
+impl Drop for JoinWakers {
+    fn drop(&mut self) {
+        // 仍注册在其它地方的子Waker不再唤醒任务
+        if let Some((shared, _)) = self.inner.as_ref() {
+            shared.parent.lock().unwrap().take();
+        }
+    }
+}
+
+/// 组合器中的子Future及其输出
+#[doc(hidden)]
+pub enum MaybeDone<F: Future> {
+    Future(Pin<Box<F>>),
+    Done(F::Output),
+    Gone,
+}
+
+impl<F: Future> MaybeDone<F> {
+    pub fn new(f: F) -> Self {
+        MaybeDone::Future(Box::pin(f))
+    }
+
+    pub fn is_done(&self) -> bool {
+        !matches!(self, MaybeDone::Future(_))
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> bool {
+        if let MaybeDone::Future(f) = self
+            && let Poll::Ready(output) = f.as_mut().poll(cx)
+        {
+            *self = MaybeDone::Done(output);
+        }
+        self.is_done()
+    }
+
+    pub fn take_output(&mut self) -> F::Output {
+        match std::mem::replace(self, MaybeDone::Gone) {
+            MaybeDone::Done(output) => output,
+            _ => panic!("MaybeDone output is not ready"),
+        }
+    }
+}
+
+impl<T, E, F: Future<Output = Result<T, E>>> MaybeDone<F> {
+    // 输出为Err时将其取出
+    pub fn take_err(&mut self) -> Option<E> {
+        match self {
+            MaybeDone::Done(Err(_)) => self.take_output().err(),
+            _ => None,
+        }
+    }
+
+    pub fn take_ok(&mut self) -> T {
+        match self.take_output() {
+            Ok(output) => output,
+            Err(_) => panic!("MaybeDone output is an error"),
+        }
+    }
+}
+
+// 并发等待全部Future，按顺序返回它们的输出
+pub fn join_all<I>(iter: I) -> JoinAll<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    let futures: Vec<_> = iter.into_iter().map(MaybeDone::new).collect();
+    JoinAll {
+        wakers: JoinWakers::new(futures.len()),
+        futures,
+    }
+}
+
+pub struct JoinAll<F: Future> {
+    futures: Vec<MaybeDone<F>>,
+    wakers: JoinWakers,
+}
+
+impl<F: Future> Future for JoinAll<F> {
+    type Output = Vec<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // 子Future都已单独固定在堆上，输出不会被固定
+        let this = unsafe { self.get_unchecked_mut() };
+        this.wakers.register(cx);
+        let mut done = true;
+        for (index, fut) in this.futures.iter_mut().enumerate() {
+            done &= this.wakers.poll_child(index, fut);
+        }
+
+        if !done {
+            return Poll::Pending;
+        }
+        Poll::Ready(
+            this.futures
+                .iter_mut()
+                .map(MaybeDone::take_output)
+                .collect(),
+        )
+    }
+}
+
+// 并发等待全部Future，任一返回Err时立即返回该错误，其余Future随之被释放
+pub fn try_join_all<I, T, E>(iter: I) -> TryJoinAll<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future<Output = Result<T, E>>,
+{
+    TryJoinAll {
+        inner: join_all(iter),
+    }
+}
+
+pub struct TryJoinAll<F: Future> {
+    inner: JoinAll<F>,
+}
+
+impl<T, E, F: Future<Output = Result<T, E>>> Future for TryJoinAll<F> {
+    type Output = Result<Vec<T>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let JoinAll { futures, wakers } = &mut unsafe { self.get_unchecked_mut() }.inner;
+        wakers.register(cx);
+        let mut done = true;
+        for (index, fut) in futures.iter_mut().enumerate() {
+            done &= wakers.poll_child(index, fut);
+            if let Some(e) = fut.take_err() {
+                futures.clear();
+                return Poll::Ready(Err(e));
+            }
+        }
+
+        if !done {
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(futures.iter_mut().map(MaybeDone::take_ok).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, time};
+
+    use crate::{
+        join, join_all,
+        result::{Error, Result},
+        sleep,
+        sync::notifier::Notifier,
+        time::now,
+        try_join, try_join_all,
+    };
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_join() {
+        let start = now();
+        let (a, b, c) = join!(
+            async {
+                sleep(time::Duration::from_millis(30)).await;
+                1
+            },
+            async {
+                sleep(time::Duration::from_millis(10)).await;
+                "b"
+            },
+            async { 'c' },
+        );
+        assert_eq!((a, b, c), (1, "b", 'c'));
+        assert_eq!(now() - start, time::Duration::from_millis(30));
+
+        let outputs = join_all((0..3u64).map(|i| async move {
+            sleep(time::Duration::from_millis(30 - i * 10)).await;
+            i
+        }))
+        .await;
+        assert_eq!(outputs, [0, 1, 2]);
+        assert_eq!(now() - start, time::Duration::from_millis(60));
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_try_join() {
+        async fn step(ms: u64, ok: bool) -> Result<u64> {
+            sleep(time::Duration::from_millis(ms)).await;
+            match ok {
+                true => Ok(ms),
+                false => Err(format!("step {} failed", ms).into()),
+            }
+        }
+
+        let start = now();
+        let outputs: Result<(u64, u64)> = try_join!(step(10, true), step(20, true));
+        assert_eq!(outputs.unwrap(), (10, 20));
+
+        // 第一个错误立即返回，不再等待其余Future
+        let start_failed = now();
+        let outputs: Result<(u64, u64, u64)> =
+            try_join!(step(1000, true), step(20, false), step(10, true));
+        assert!(format!("{:?}", outputs.unwrap_err()).contains("step 20 failed"));
+        assert_eq!(now() - start_failed, time::Duration::from_millis(20));
+
+        let outputs = try_join_all([step(10, true), step(30, true)]).await;
+        assert_eq!(outputs.unwrap(), [10, 30]);
+        let outputs: std::result::Result<Vec<_>, Error> =
+            try_join_all([step(1000, true), step(10, false)]).await;
+        assert!(outputs.is_err());
+        assert_eq!(now() - start, time::Duration::from_millis(80));
+    }
+
+    #[rt_entry::test]
+    async fn test_join_wakeup() {
+        // 只有被唤醒的子Future会被重新poll
+        let polls = Rc::new([Cell::new(0), Cell::new(0)]);
+        let notifiers = Rc::new([Notifier::new(), Notifier::new()]);
+
+        let child = |index: usize| {
+            let (polls, notifiers) = (polls.clone(), notifiers.clone());
+            async move {
+                let mut wait = std::pin::pin!(notifiers[index].wait());
+                crate::helper::poll_fn(|cx| {
+                    polls[index].set(polls[index].get() + 1);
+                    wait.as_mut().poll(cx)
+                })
+                .await;
+            }
+        };
+
+        let n = notifiers.clone();
+        spawn!(async move {
+            for notifier in n.iter() {
+                sleep(time::Duration::from_millis(10)).await;
+                notifier.notify_all();
+            }
+        })
+        .detach();
+
+        join!(child(0), child(1));
+        assert_eq!(polls[0].get(), 2);
+        assert_eq!(polls[1].get(), 2);
+    }
+}
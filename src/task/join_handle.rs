@@ -0,0 +1,243 @@
+use std::{
+    marker::PhantomData,
+    rc::Rc,
+    task::{Poll, Waker},
+};
+
+use crate::{
+    collections::ShareMutable,
+    result::{ErrorType, Result},
+    runtime::{add_waker, dump::WaitOn, wait_on},
+    task::TaskStatus,
+};
+
+/*
+ * 任务与JoinHandle之间共享的状态
+ * 任务完成时写入输出并唤醒等待者；任务在完成前被释放时标记为取消
+ */
+pub(crate) struct JoinState<T> {
+    output: Option<T>,
+    status: TaskStatus,
+    // 等待任务结束的Waker（只会有一个JoinHandle）
+    waker: Option<Waker>,
+}
+
+impl<T> JoinState<T> {
+    pub(crate) fn new() -> ShareMutable<Self> {
+        ShareMutable::new(Self {
+            output: None,
+            status: TaskStatus::Running,
+            waker: None,
+        })
+    }
+
+    pub(crate) fn complete(&mut self, output: T) {
+        self.output.replace(output);
+        self.finish(TaskStatus::Completed);
+    }
+
+    pub(crate) fn cancel(&mut self) {
+        self.finish(TaskStatus::Cancelled);
+    }
+
+    pub(crate) fn cancelled(&self) -> bool {
+        self.status.cancelled()
+    }
+
+    fn finish(&mut self, status: TaskStatus) {
+        if self.status.finished() {
+            return;
+        }
+
+        self.status = status;
+        if let Some(waker) = self.waker.take() {
+            add_waker(waker);
+        }
+    }
+}
+
+/// spawn返回的任务句柄，可以通过await获取任务的输出
+/// 直接drop或调用detach()都不会影响任务的执行
+pub struct JoinHandle<T> {
+    state: ShareMutable<JoinState<T>>,
+    abort_handle: AbortHandle,
+}
+
+impl<T> JoinHandle<T> {
+    pub(crate) fn new(state: ShareMutable<JoinState<T>>, abort_handle: AbortHandle) -> Self {
+        Self {
+            state,
+            abort_handle,
+        }
+    }
+
+    // 取消任务，之后await将得到ErrorType::Cancelled
+    pub fn abort(&self) {
+        self.abort_handle.abort();
+    }
+
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.abort_handle.clone()
+    }
+
+    // 任务已结束（完成或被取消）
+    pub fn is_finished(&self) -> bool {
+        self.state.borrow().status.finished()
+    }
+
+    pub fn is_completed(&self) -> bool {
+        matches!(self.state.borrow().status, TaskStatus::Completed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.borrow().status.cancelled()
+    }
+
+    // 放弃对任务输出的等待，任务继续在后台执行
+    pub fn detach(self) {}
+
+    // 任务结束时获取其输出
+    pub(crate) fn take_output(&mut self) -> Option<Result<T>> {
+        let mut state = self.state.borrow_mut();
+        match state.status {
+            TaskStatus::Running => None,
+            TaskStatus::Cancelled => Some(Err(ErrorType::Cancelled.into())),
+            TaskStatus::Completed => Some(
+                state
+                    .output
+                    .take()
+                    .ok_or_else(|| "task output has been taken".into()),
+            ),
+        }
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T>;
+
+    fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match this.take_output() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                this.state.borrow_mut().waker.replace(cx.waker().clone());
+                wait_on(WaitOn::Join);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        // 不再等待任务结束，避免任务结束时唤醒已不关心结果的任务（如select!中未命中的分支）
+        self.state.borrow_mut().waker.take();
+    }
+}
+
+/*
+ * 任务取消句柄
+ * 持有任务Waker的一个副本，通过单态化的abort函数指针反解析出具体的任务类型
+ */
+#[derive(Clone)]
+pub struct AbortHandle {
+    waker: Waker,
+    abort: fn(*const ()),
+    // abort会直接操作任务内部的Rc，只能在任务所属的线程中使用
+    _not_send: PhantomData<Rc<()>>,
+}
+
+impl AbortHandle {
+    pub(crate) fn new(waker: Waker, abort: fn(*const ())) -> Self {
+        Self {
+            waker,
+            abort,
+            _not_send: PhantomData,
+        }
+    }
+
+    // 将任务置为Cancelled，释放其future并从运行时中移除。对已结束的任务无影响
+    pub fn abort(&self) {
+        (self.abort)(self.waker.data());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time;
+
+    use std::{cell::Cell, rc::Rc};
+
+    use crate::{result::Result, sleep, sync::mutex::AsyncMutex, task::join_handle::AbortHandle};
+
+    #[rt_entry::test]
+    async fn test_join_handle() -> Result<()> {
+        let handle = spawn!(async {
+            sleep(time::Duration::from_millis(100)).await;
+            1 + 1
+        });
+        assert!(!handle.is_finished());
+        assert_eq!(handle.await?, 2);
+
+        let handle = spawn!(async { "ready" });
+        sleep(time::Duration::from_millis(10)).await;
+        assert!(handle.is_completed());
+        assert_eq!(handle.await?, "ready");
+
+        Ok(())
+    }
+
+    #[rt_entry::test]
+    async fn test_detach() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawn!(async move {
+            sleep(time::Duration::from_millis(50)).await;
+            tx.send(()).unwrap();
+        })
+        .detach();
+
+        sleep(time::Duration::from_millis(100)).await;
+        assert!(rx.try_recv().is_ok());
+    }
+
+    #[rt_entry::test]
+    async fn test_abort() -> Result<()> {
+        struct DropFlag(Rc<Cell<bool>>);
+
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let mtx = Rc::new(AsyncMutex::new(0));
+        let dropped = Rc::new(Cell::new(false));
+
+        let (m, flag) = (mtx.clone(), DropFlag(dropped.clone()));
+        let handle = spawn!(async move {
+            let _flag = flag;
+            let _guard = m.lock().await;
+            sleep(time::Duration::from_secs(10)).await;
+        });
+        sleep(time::Duration::from_millis(50)).await;
+
+        handle.abort();
+        assert!(dropped.get());
+        assert!(handle.is_cancelled());
+        assert!(handle.await.is_err_and(|e| e.is_cancelled()));
+        // 被取消任务持有的锁已经释放
+        *mtx.lock().await += 1;
+
+        // 任务在自身poll的过程中被取消
+        let slot: Rc<Cell<Option<AbortHandle>>> = Rc::new(Cell::new(None));
+        let s = slot.clone();
+        let handle = spawn!(async move {
+            s.take().unwrap().abort();
+            sleep(time::Duration::from_secs(10)).await;
+        });
+        slot.set(Some(handle.abort_handle()));
+        assert!(handle.await.is_err_and(|e| e.is_cancelled()));
+
+        Ok(())
+    }
+}
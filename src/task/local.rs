@@ -0,0 +1,144 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use crate::{result::Result, runtime::with_polling};
+
+// 任务本地变量的存储，以LocalKey的地址作为key
+pub(crate) type TaskLocals = HashMap<usize, Rc<dyn Any>>;
+
+/*
+ * 任务本地变量的key，值保存在任务的TaskAttr中，随任务的Waker共享
+ * scope每次poll时将值写入当前任务，poll结束后恢复外层scope的值，因此同一任务中的嵌套scope互不影响
+ */
+pub struct LocalKey<T: 'static> {
+    // 同时保证每个静态变量的地址不同
+    name: &'static str,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static> LocalKey<T> {
+    #[doc(hidden)]
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _marker: PhantomData,
+        }
+    }
+
+    fn id(&'static self) -> usize {
+        self as *const Self as usize
+    }
+
+    // 在执行fut的过程中将变量设置为value
+    pub fn scope<F: Future>(&'static self, value: T, fut: F) -> TaskLocalFuture<T, F> {
+        TaskLocalFuture {
+            key: self,
+            value: Rc::new(value),
+            fut,
+        }
+    }
+
+    // 以变量的值调用f，不在scope中时panic
+    pub fn with<R>(&'static self, f: impl FnOnce(&T) -> R) -> R {
+        match self.try_with(f) {
+            Ok(output) => output,
+            Err(_) => panic!("task local {} is not set", self.name),
+        }
+    }
+
+    pub fn try_with<R>(&'static self, f: impl FnOnce(&T) -> R) -> Result<R> {
+        // 先取出值再调用f，保证f中可以再次访问任务本地变量
+        let value = with_polling(|attr| attr.locals().borrow().get(&self.id()).cloned()).flatten();
+        match value.as_ref().and_then(|value| value.downcast_ref::<T>()) {
+            Some(value) => Ok(f(value)),
+            None => Err(format!("task local {} is not set", self.name).into()),
+        }
+    }
+
+    pub fn get(&'static self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+}
+
+pub struct TaskLocalFuture<T: 'static, F> {
+    key: &'static LocalKey<T>,
+    value: Rc<T>,
+    fut: F,
+}
+
+impl<T: 'static, F: Future> Future for TaskLocalFuture<T, F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // fut不会被移动
+        let this = unsafe { self.get_unchecked_mut() };
+        let id = this.key.id();
+        let prev = with_polling(|attr| {
+            attr.locals()
+                .borrow_mut()
+                .insert(id, this.value.clone() as Rc<dyn Any>)
+        });
+
+        let output = unsafe { Pin::new_unchecked(&mut this.fut) }.poll(cx);
+
+        if let Some(prev) = prev {
+            with_polling(|attr| {
+                let mut locals = attr.locals().borrow_mut();
+                match prev {
+                    Some(prev) => locals.insert(id, prev),
+                    None => locals.remove(&id),
+                }
+            });
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time;
+
+    use crate::{REQUEST_ID, helper::yield_here, sleep, task_local};
+
+    task_local! {
+        static DEPTH: usize;
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_task_local() {
+        assert!(REQUEST_ID.try_with(|_| ()).is_err());
+
+        let handles: Vec<_> = (0..3)
+            .map(|i| {
+                spawn!(REQUEST_ID.scope(format!("req-{}", i), async move {
+                    // 跨越await后仍然可以访问，且任务之间互不影响
+                    sleep(time::Duration::from_millis(10 * (3 - i))).await;
+                    let id = REQUEST_ID.get();
+
+                    let inner = DEPTH
+                        .scope(1, async {
+                            yield_here().await;
+                            DEPTH.scope(2, async { DEPTH.get() }).await + DEPTH.get()
+                        })
+                        .await;
+                    assert!(DEPTH.try_with(|_| ()).is_err());
+                    (id, inner)
+                }))
+            })
+            .collect();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.await.unwrap(), (format!("req-{}", i), 3));
+        }
+        assert!(REQUEST_ID.try_with(|_| ()).is_err());
+    }
+}
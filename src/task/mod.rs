@@ -1,14 +1,28 @@
+pub mod join;
+pub mod join_handle;
+pub mod local;
+pub mod scope;
 pub mod task_id;
 pub mod waker_ext;
 
 use std::{
     pin::Pin,
+    ptr,
     task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    time,
 };
 
 use crate::{
     collections::ShareMutable,
-    task::task_id::{TaskId, alloc_id},
+    runtime::{
+        poll_end, poll_start, remote,
+        remote::{RemoteHandle, thread_mark},
+    },
+    task::{
+        join_handle::{AbortHandle, JoinHandle, JoinState},
+        local::TaskLocals,
+        task_id::{TaskId, alloc_id},
+    },
 };
 
 #[derive(Debug)]
@@ -20,12 +34,12 @@ pub enum TaskStatus {
 
 impl TaskStatus {
     #[inline]
-    fn cancelled(&self) -> bool {
+    pub(crate) fn cancelled(&self) -> bool {
         matches!(self, Self::Cancelled)
     }
 
     #[inline]
-    fn finished(&self) -> bool {
+    pub(crate) fn finished(&self) -> bool {
         matches!(self, Self::Cancelled | Self::Completed)
     }
 }
@@ -34,8 +48,10 @@ impl TaskStatus {
 // 任务属性，避免泛型带来的反解析问题
 pub struct TaskAttr {
     tid: TaskId,
-    // 支持共享状态的修改或独立状态的修改
+    // 任务的全部Waker共享同一个状态
     status: ShareMutable<TaskStatus>,
+    // 任务本地变量，随任务的全部TaskAttr共享
+    locals: ShareMutable<TaskLocals>,
 }
 
 impl TaskAttr {
@@ -43,6 +59,7 @@ impl TaskAttr {
         TaskAttr {
             tid: alloc_id(),
             status: ShareMutable::new(TaskStatus::Running),
+            locals: ShareMutable::default(),
         }
     }
 
@@ -57,14 +74,13 @@ impl TaskAttr {
         &self.tid
     }
 
-    // 共享状态修改
-    pub fn update_status(&self, status: TaskStatus) {
-        *self.status.borrow_mut() = status;
+    pub(crate) fn locals(&self) -> &ShareMutable<TaskLocals> {
+        &self.locals
     }
 
-    // 单独状态修改
-    pub fn set_status(&mut self, status: TaskStatus) {
-        self.status = ShareMutable::new(status);
+    // 状态在任务的全部Waker之间共享
+    pub fn update_status(&self, status: TaskStatus) {
+        *self.status.borrow_mut() = status;
     }
 
     #[allow(unused)]
@@ -82,6 +98,7 @@ impl Clone for TaskAttr {
         Self {
             tid: self.tid.clone(),
             status: self.status.clone(),
+            locals: self.locals.clone(),
         }
     }
 }
@@ -91,37 +108,106 @@ pub trait TTaskClear {
 }
 
 struct TaskInner<F: Future, C: TTaskClear> {
-    result: Option<F::Output>,
-    fut: Pin<Box<F>>,
+    // 与JoinHandle共享，用于传递任务的输出
+    join: ShareMutable<JoinState<F::Output>>,
+    // 任务完成或被取消后置空，保证资源及时释放
+    fut: Option<Pin<Box<F>>>,
     clear: C,
 }
 
+impl<F: Future, C: TTaskClear> TaskInner<F, C> {
+    // 释放future，并将任务从运行时中移除
+    fn release(&mut self) {
+        let _ = self.fut.take();
+        self.clear.clear();
+    }
+}
+
 impl<F: Future, C: TTaskClear> Drop for TaskInner<F, C> {
     fn drop(&mut self) {
+        // 未完成就被释放的任务视为被取消
+        self.join.borrow_mut().cancel();
         self.clear.clear();
     }
 }
 
+/*
+ * 其它线程上的clone先按位复制attr、inner与join，再由所属线程补充引用计数（见Task::clone与Task::retain）
+ * 这要求它们的每个字段都只是一个Rc句柄：按位复制后再clone一次并forget，等价于一次正常的clone
+ * 通过RcHandle在编译期检查，新增不满足要求的字段时无法编译
+ */
+/// # Safety
+///
+// 只能为内部仅有一个Rc的句柄类型实现
+unsafe trait RcHandle {}
+
+unsafe impl<T> RcHandle for std::rc::Rc<T> {}
+
+unsafe impl<T> RcHandle for ShareMutable<T> {}
+
+#[allow(unused)]
+fn assert_rc_handles<F: Future, C: TTaskClear>(task: &Task<F, C>) {
+    fn rc_handle<T: RcHandle>(_: &T) {}
+
+    // 完整解构，新增字段时必须在这里声明
+    let Task {
+        attr,
+        inner,
+        join,
+        remote: _,
+        thread: _,
+    } = task;
+    let TaskAttr {
+        tid,
+        status,
+        locals,
+    } = attr;
+    rc_handle(tid);
+    rc_handle(status);
+    rc_handle(locals);
+    rc_handle(inner);
+    rc_handle(join);
+}
+
 #[repr(C)]
 pub struct Task<F: Future, C: TTaskClear> {
     attr: TaskAttr,
     inner: ShareMutable<TaskInner<F, C>>,
+    // 放在inner之外，保证任务在poll过程中也可以被取消
+    join: ShareMutable<JoinState<F::Output>>,
+    // 任务所属运行时的远程句柄及其线程，其它线程上对Waker的操作都转交给所属线程执行
+    remote: RemoteHandle,
+    thread: usize,
 }
 
 impl<F: Future, C: TTaskClear> Task<F, C> {
-    pub fn new_waker(f: F, mut init_factory: impl FnMut(TaskId) -> C) -> Waker {
+    pub fn new_waker(
+        f: F,
+        mut init_factory: impl FnMut(TaskId) -> C,
+    ) -> (Waker, JoinHandle<F::Output>) {
         let attr = TaskAttr::new();
         let clear = init_factory(attr.tid.clone());
+        let join = JoinState::new();
         let task = Box::new(Self {
             attr,
             inner: ShareMutable::new(TaskInner {
-                result: None,
-                fut: Box::pin(f),
+                join: join.clone(),
+                fut: Some(Box::pin(f)),
                 clear,
             }),
+            join: join.clone(),
+            remote: remote(),
+            thread: thread_mark(),
         });
 
-        unsafe { Waker::new(Box::into_raw(task) as *const (), Self::waker_vtable()) }
+        let waker = unsafe { Waker::new(Box::into_raw(task) as *const (), Self::waker_vtable()) };
+        let abort_handle = AbortHandle::new(waker.clone(), Self::abort);
+        (waker, JoinHandle::new(join, abort_handle))
+    }
+
+    // 是否在任务所属的线程之外
+    fn foreign(&self) -> bool {
+        self.thread != thread_mark()
     }
 
     /*
@@ -132,7 +218,23 @@ impl<F: Future, C: TTaskClear> Task<F, C> {
         // 从Box::into_raw的源码可知获取到的就是包裹的数据在堆上的地址，因此可以直接解引用使用
         let task = unsafe { &*(data as *const Self) };
         // 需要将新副本仍然分配到堆上
-        let task_cloned = Box::new(task.clone());
+        let task_cloned = if task.foreign() {
+            // 其它线程上不能修改Rc的引用计数，先按位复制，再由所属线程补充引用计数（字段要求见RcHandle）
+            let task_cloned = Box::new(unsafe {
+                Self {
+                    attr: ptr::read(&task.attr),
+                    inner: ptr::read(&task.inner),
+                    join: ptr::read(&task.join),
+                    remote: task.remote.clone(),
+                    thread: task.thread,
+                }
+            });
+            let data = &*task_cloned as *const Self as *const ();
+            task.remote.push_retain(data, Self::retain);
+            task_cloned
+        } else {
+            Box::new(task.clone())
+        };
         // 新副本被用于clone的新对象
         RawWaker::new(
             Box::into_raw(task_cloned) as *const (),
@@ -140,14 +242,33 @@ impl<F: Future, C: TTaskClear> Task<F, C> {
         )
     }
 
+    // 为其它线程上按位复制的副本补充引用计数
+    fn retain(data: *const ()) {
+        let task = unsafe { &*(data as *const Self) };
+        std::mem::forget((task.attr.clone(), task.inner.clone(), task.join.clone()));
+    }
+
     // 需要保证自身被消费掉
     fn wake(data: *const ()) {
+        let task = unsafe { &*(data as *const Self) };
+        if task.foreign() {
+            let remote = task.remote.clone();
+            remote.push_wake(unsafe { Waker::new(data, Self::waker_vtable()) });
+            return;
+        }
+
         Self::wake_by_ref(data);
         Self::drop(data);
     }
 
     fn wake_by_ref(data: *const ()) {
         let task = unsafe { &mut *(data as *mut Self) };
+        if task.foreign() {
+            let waker = unsafe { Waker::from_raw(Self::clone(data)) };
+            task.remote.push_wake(waker);
+            return;
+        }
+
         if task.attr.finished() {
             log::debug!("task has finished");
             return;
@@ -158,14 +279,46 @@ impl<F: Future, C: TTaskClear> Task<F, C> {
         let waker = unsafe { Waker::from_raw(Self::clone(data)) };
         let mut cx = Context::from_waker(&waker);
         let mut task_inner = task.inner.borrow_mut();
-        if let Poll::Ready(result) = task_inner.fut.as_mut().poll(&mut cx) {
+        let Some(fut) = task_inner.fut.as_mut() else {
+            log::debug!("task has been aborted");
+            return;
+        };
+        let prev = poll_start(&task.attr);
+        let start = time::Instant::now();
+        let poll = fut.as_mut().poll(&mut cx);
+        poll_end(&task.attr, prev, start.elapsed());
+        if let Poll::Ready(result) = poll {
             task.attr.update_status(TaskStatus::Completed);
-            task_inner.result.replace(result);
+            task.join.borrow_mut().complete(result);
+            task_inner.release();
+        } else if task.join.borrow().cancelled() {
+            // 任务在自身的poll过程中被取消
+            task_inner.release();
+        }
+    }
+
+    // 取消任务：立即释放future（触发其中各类Drop），并从运行时中移除
+    fn abort(data: *const ()) {
+        let task = unsafe { &*(data as *const Self) };
+        task.attr.update_status(TaskStatus::Cancelled);
+        task.join.borrow_mut().cancel();
+        // 借用失败说明任务正处于poll中，会在poll结束后释放
+        if let Some(mut task_inner) = task.inner.try_borrow_mut() {
+            task_inner.release();
         }
     }
 
     // 引用计数减一，并保证资源正确释放
     fn drop(data: *const ()) {
+        let task = unsafe { &*(data as *const Self) };
+        if task.foreign() {
+            let remote = task.remote.clone();
+            remote.push_drop(unsafe { Waker::new(data, Self::waker_vtable()) });
+            return;
+        }
+
+        // 其它线程clone出的副本可能还未补充引用计数，需要先补充，避免提前释放
+        task.remote.apply_retains();
         let _ = unsafe { Box::from_raw(data as *mut Self) };
     }
 
@@ -184,6 +337,9 @@ impl<F: Future, C: TTaskClear> Clone for Task<F, C> {
         Self {
             attr: self.attr.clone(),
             inner: self.inner.clone(),
+            join: self.join.clone(),
+            remote: self.remote.clone(),
+            thread: self.thread,
         }
     }
 }
@@ -0,0 +1,271 @@
+use std::{
+    collections::HashMap,
+    panic::Location,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    collections::ShareMutable,
+    result::{Error, ErrorType, Result},
+    runtime::{add_waker, dump::WaitOn, spawn_at, wait_on},
+    task::join_handle::AbortHandle,
+};
+
+struct ScopeState<T> {
+    // 按提交顺序保存子任务的输出
+    outputs: Vec<Option<T>>,
+    // 未结束的子任务，用于取消
+    children: HashMap<usize, AbortHandle>,
+    // 未结束的子任务数量，子任务被取消时同样会减少
+    pending: usize,
+    // 第一个失败的子任务的错误
+    error: Option<Error>,
+    cancelled: bool,
+    // 等待全部子任务结束的Waker（只会有一个join）
+    waker: Option<Waker>,
+}
+
+/*
+ * 结构化并发的任务域：子任务提交到域中，join在全部子任务结束后返回它们的输出
+ * 任一子任务失败时取消其余子任务，join返回该错误；域被drop时取消仍在执行的子任务
+ * 域可能被mem::forget而不执行取消，因此子任务与普通任务一样需要满足'static，不能借用域外的数据
+ */
+pub struct TaskScope<T> {
+    state: ShareMutable<ScopeState<T>>,
+}
+
+impl<T> Default for TaskScope<T> {
+    fn default() -> Self {
+        Self {
+            state: ShareMutable::new(ScopeState {
+                outputs: Vec::new(),
+                children: HashMap::new(),
+                pending: 0,
+                error: None,
+                cancelled: false,
+                waker: None,
+            }),
+        }
+    }
+}
+
+impl<T> TaskScope<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 提交子任务，域已被取消时直接丢弃
+    #[track_caller]
+    pub fn spawn<F: Future<Output = Result<T>> + 'static>(&self, f: F)
+    where
+        T: 'static,
+    {
+        let index = {
+            let mut state = self.state.borrow_mut();
+            if state.cancelled {
+                return;
+            }
+            state.outputs.push(None);
+            state.pending += 1;
+            state.outputs.len() - 1
+        };
+
+        let guard = ChildGuard {
+            state: self.state.clone(),
+            index,
+        };
+        let handle = spawn_at(
+            async move {
+                let output = f.await;
+                guard.finish(output);
+            },
+            None,
+            Location::caller(),
+        );
+        self.state
+            .borrow_mut()
+            .children
+            .insert(index, handle.abort_handle());
+    }
+
+    // 取消全部未结束的子任务，之后提交的子任务会被直接丢弃
+    pub fn cancel(&self) {
+        let children: Vec<_> = {
+            let mut state = self.state.borrow_mut();
+            state.cancelled = true;
+            state.children.drain().map(|(_, handle)| handle).collect()
+        };
+        // 取消时会同步释放子任务，需要在借用结束后执行
+        for handle in children {
+            handle.abort();
+        }
+    }
+
+    // 未结束的子任务数量
+    pub fn pending(&self) -> usize {
+        self.state.borrow().pending
+    }
+
+    /*
+     * 等待全部子任务结束
+     * 全部成功时按提交顺序返回输出；有子任务失败时返回第一个错误；被cancel时返回ErrorType::Cancelled
+     */
+    pub fn join(&self) -> ScopeJoin<'_, T> {
+        ScopeJoin { scope: self }
+    }
+}
+
+impl<T> Drop for TaskScope<T> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// 子任务结束（完成或被取消）时更新域的状态
+struct ChildGuard<T> {
+    state: ShareMutable<ScopeState<T>>,
+    index: usize,
+}
+
+impl<T> ChildGuard<T> {
+    fn finish(&self, output: Result<T>) {
+        let siblings: Vec<_> = {
+            let mut state = self.state.borrow_mut();
+            state.children.remove(&self.index);
+            match output {
+                Ok(output) => {
+                    state.outputs[self.index] = Some(output);
+                    Vec::new()
+                }
+                Err(e) => {
+                    if state.error.is_none() && !state.cancelled {
+                        state.error = Some(e);
+                    }
+                    state.cancelled = true;
+                    state.children.drain().map(|(_, handle)| handle).collect()
+                }
+            }
+        };
+        for handle in siblings {
+            handle.abort();
+        }
+    }
+}
+
+impl<T> Drop for ChildGuard<T> {
+    fn drop(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.pending -= 1;
+        if state.pending == 0
+            && let Some(waker) = state.waker.take()
+        {
+            add_waker(waker);
+        }
+    }
+}
+
+pub struct ScopeJoin<'a, T> {
+    scope: &'a TaskScope<T>,
+}
+
+impl<T> Future for ScopeJoin<'_, T> {
+    type Output = Result<Vec<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.scope.state.borrow_mut();
+        if state.pending > 0 {
+            state.waker.replace(cx.waker().clone());
+            wait_on(WaitOn::Join);
+            return Poll::Pending;
+        }
+
+        if let Some(e) = state.error.take() {
+            return Poll::Ready(Err(e));
+        }
+        if state.cancelled {
+            return Poll::Ready(Err(ErrorType::Cancelled.into()));
+        }
+        let outputs = std::mem::take(&mut state.outputs);
+        Poll::Ready(
+            outputs
+                .into_iter()
+                .map(|output| output.ok_or_else(|| "scope output has been taken".into()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, time};
+
+    use crate::{sleep, task::scope::TaskScope, time::now};
+
+    struct DropFlag(Rc<Cell<bool>>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_task_scope() {
+        // 输出按提交顺序返回
+        let names = ["a".to_owned(), "b".to_owned(), "c".to_owned()];
+        let scope = TaskScope::new();
+        for (i, name) in names.into_iter().enumerate() {
+            scope.spawn(async move {
+                sleep(time::Duration::from_millis(30 - i as u64 * 10)).await;
+                Ok(name)
+            });
+        }
+        assert_eq!(scope.pending(), 3);
+        assert_eq!(scope.join().await.unwrap(), ["a", "b", "c"]);
+        assert_eq!(scope.pending(), 0);
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_task_scope_failed() {
+        let dropped = Rc::new(Cell::new(false));
+        let scope = TaskScope::new();
+        let flag = DropFlag(dropped.clone());
+        scope.spawn(async move {
+            let _flag = flag;
+            sleep(time::Duration::from_secs(10)).await;
+            Ok(())
+        });
+        scope.spawn(async {
+            sleep(time::Duration::from_millis(10)).await;
+            Err("lookup failed".into())
+        });
+
+        // 子任务失败时其余子任务被取消
+        let start = now();
+        let err = scope.join().await.unwrap_err();
+        assert!(format!("{:?}", err).contains("lookup failed"));
+        assert!(dropped.get());
+        assert_eq!(now() - start, time::Duration::from_millis(10));
+
+        // 已失败的域不再执行新的子任务
+        scope.spawn(async { Ok(()) });
+        assert_eq!(scope.pending(), 0);
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_task_scope_drop() {
+        let dropped = Rc::new(Cell::new(false));
+        let scope = TaskScope::new();
+        let flag = DropFlag(dropped.clone());
+        scope.spawn(async move {
+            let _flag = flag;
+            sleep(time::Duration::from_secs(10)).await;
+            Ok(())
+        });
+        sleep(time::Duration::from_millis(10)).await;
+
+        drop(scope);
+        assert!(dropped.get());
+    }
+}
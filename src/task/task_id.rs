@@ -1,15 +1,18 @@
-use std::{fmt::Display, rc::Rc};
-
-use lazy_static::lazy_static;
-
-use crate::helper::UPSafeCell;
+use std::{cell::RefCell, fmt::Display, rc::Rc};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct _TaskId(usize);
 
+impl _TaskId {
+    pub(crate) fn value(&self) -> usize {
+        self.0
+    }
+}
+
 impl Drop for _TaskId {
     fn drop(&mut self) {
-        TASK_ID_GEN.exclusive_access().recycle_use(self.0);
+        // 线程退出时生成器可能已被销毁，此时无需回收
+        let _ = TASK_ID_GEN.try_with(|id_gen| id_gen.borrow_mut().recycle_use(self.0));
     }
 }
 
@@ -47,11 +50,11 @@ impl TaskIdGenerator {
     }
 }
 
-lazy_static! {
-    static ref TASK_ID_GEN: UPSafeCell<TaskIdGenerator> =
-        UPSafeCell::new(TaskIdGenerator::default());
+// task_id只在线程内唯一，TaskId本身也无法跨线程使用
+thread_local! {
+    static TASK_ID_GEN: RefCell<TaskIdGenerator> = RefCell::new(TaskIdGenerator::default());
 }
 
 pub(crate) fn alloc_id() -> TaskId {
-    TASK_ID_GEN.exclusive_access().gen_id()
+    TASK_ID_GEN.with(|id_gen| id_gen.borrow_mut().gen_id())
 }
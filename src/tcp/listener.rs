@@ -33,6 +33,10 @@ impl Listener {
         Ok(())
     }
 
+    pub fn local_addr(&self) -> Result<std::net::SocketAddr> {
+        Ok(self.tcp_listener.local_addr()?)
+    }
+
     pub fn accept(&mut self) -> Result<(mio::net::TcpStream, std::net::SocketAddr)> {
         Ok(self.tcp_listener.accept()?)
     }
@@ -46,7 +50,7 @@ impl Display for Listener {
 
 impl Drop for Listener {
     fn drop(&mut self) {
-        if let Err(e) = deregister(&mut self.tcp_listener) {
+        if let Err(e) = deregister(&self.io_event, &mut self.tcp_listener) {
             log::warn!("deregister {} failed: {:?}", self, e);
         }
 
@@ -80,7 +80,7 @@ impl Display for Stream {
 
 impl Drop for Stream {
     fn drop(&mut self) {
-        if let Err(e) = deregister(&mut self.tcp_stream) {
+        if let Err(e) = deregister(&self.io_event, &mut self.tcp_stream) {
             log::warn!("deregister {} failed: {:?}", self, e);
         }
         log::debug!("{} disconnected", self);
@@ -0,0 +1,153 @@
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+use crate::{helper::yield_here, runtime};
+
+/*
+ * 运行时的时钟，定时器、超时与周期定时器都基于它计算时间
+ * 暂停后时间冻结，只能通过advance推进；所有任务都在等待定时器时，运行时会直接将时钟推进到最近的定时器
+ */
+pub(crate) struct Clock {
+    // 时钟的起点，也是定时器tick的起点
+    origin: Instant,
+    // 暂停时冻结的时间
+    frozen: Cell<Option<Instant>>,
+    // 暂停期间推进的时长，恢复后继续计入，保证时间不会倒退
+    offset: Cell<Duration>,
+}
+
+impl Clock {
+    pub(crate) fn new(start_paused: bool) -> Self {
+        let clock = Self {
+            origin: Instant::now(),
+            frozen: Cell::new(None),
+            offset: Cell::new(Duration::ZERO),
+        };
+        if start_paused {
+            clock.pause();
+        }
+        clock
+    }
+
+    pub(crate) fn origin(&self) -> Instant {
+        self.origin
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        self.frozen
+            .get()
+            .unwrap_or_else(|| Instant::now() + self.offset.get())
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.frozen.get().is_some()
+    }
+
+    // 冻结的时间向上对齐到整毫秒（定时器的tick），之后以整毫秒推进的定时器都能恰好到期
+    pub(crate) fn pause(&self) {
+        if !self.is_paused() {
+            let elapsed = self.now() - self.origin;
+            let ms = elapsed.as_nanos().div_ceil(1_000_000) as u64;
+            self.frozen
+                .set(Some(self.origin + Duration::from_millis(ms)));
+        }
+    }
+
+    pub(crate) fn resume(&self) {
+        if let Some(frozen) = self.frozen.take() {
+            self.offset
+                .set(frozen.saturating_duration_since(Instant::now()));
+        }
+    }
+
+    pub(crate) fn advance(&self, dur: Duration) {
+        let frozen = self.frozen.get().expect("time is not paused");
+        self.frozen.set(Some(frozen + dur));
+    }
+}
+
+/// 当前运行时的时间，时钟暂停时返回冻结的时间
+pub fn now() -> Instant {
+    runtime::now()
+}
+
+/// 暂停当前运行时的时钟
+pub fn pause() {
+    runtime::clock(Clock::pause);
+}
+
+/// 恢复当前运行时的时钟，之后从暂停时的时间继续流逝
+pub fn resume() {
+    runtime::clock(Clock::resume);
+}
+
+pub fn is_paused() -> bool {
+    runtime::clock(Clock::is_paused)
+}
+
+/// 将暂停的时钟推进dur，到期的定时器会在返回前被执行
+/// 时钟未暂停时panic
+pub async fn advance(dur: Duration) {
+    // 推进前后各让出一次执行：先让已就绪的任务注册定时器，再让被唤醒的任务先于当前任务执行
+    yield_here().await;
+    runtime::advance_clock(dur);
+    yield_here().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+
+    use crate::{sleep, time};
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_auto_advance() {
+        let start = time::now();
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let handles: Vec<_> = [3600, 10, 60]
+            .into_iter()
+            .map(|secs| {
+                let order = order.clone();
+                spawn!(async move {
+                    sleep(Duration::from_secs(secs)).await;
+                    order.borrow_mut().push(secs);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        // 所有任务都在等待定时器时，时钟直接推进到最近的定时器
+        assert_eq!(*order.borrow(), vec![10, 60, 3600]);
+        assert_eq!(time::now() - start, Duration::from_secs(3600));
+    }
+
+    #[rt_entry::test(start_paused = true)]
+    async fn test_advance() {
+        let start = time::now();
+        let fired = Rc::new(RefCell::new(Vec::new()));
+        for ms in [100, 200] {
+            let fired = fired.clone();
+            spawn!(async move {
+                sleep(Duration::from_millis(ms)).await;
+                fired.borrow_mut().push(ms);
+            })
+            .detach();
+        }
+
+        time::advance(Duration::from_millis(150)).await;
+        assert_eq!(*fired.borrow(), vec![100]);
+        assert_eq!(time::now() - start, Duration::from_millis(150));
+
+        time::advance(Duration::from_millis(50)).await;
+        assert_eq!(*fired.borrow(), vec![100, 200]);
+
+        // 恢复后时间从暂停时的位置继续流逝
+        time::resume();
+        assert!(!time::is_paused());
+        assert!(time::now() - start >= Duration::from_millis(200));
+    }
+}
@@ -1,6 +1,50 @@
-use std::time;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time,
+};
+
+use crate::{
+    config,
+    result::{ErrorType, Result},
+    timer::Sleeper,
+};
+
+/// 为fut设置最大等待时长，超时后得到ErrorType::Timeout
+pub fn timeout<F: Future>(dur: time::Duration, fut: F) -> TimeoutFuture<F> {
+    timeout_at(crate::time::now() + dur, fut)
+}
 
-use crate::{config, sleep};
+/// 为fut设置截止时间，超时后得到ErrorType::Timeout
+pub fn timeout_at<F: Future>(deadline: time::Instant, fut: F) -> TimeoutFuture<F> {
+    TimeoutFuture {
+        fut,
+        sleeper: Sleeper::until(deadline),
+    }
+}
+
+// fut先于定时器完成时输出其结果，定时器随TimeoutFuture的释放而取消
+pub struct TimeoutFuture<F> {
+    fut: F,
+    sleeper: Sleeper,
+}
+
+impl<F: Future> Future for TimeoutFuture<F> {
+    type Output = Result<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // fut不会被移动，sleeper满足Unpin
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Poll::Ready(output) = unsafe { Pin::new_unchecked(&mut this.fut) }.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        match Pin::new(&mut this.sleeper).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(ErrorType::Timeout.into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
 
 /// 非一次性超时
 pub struct Timeout {
@@ -11,18 +55,13 @@ pub struct Timeout {
 impl Timeout {
     pub fn new(timeout: time::Duration) -> Self {
         Self {
-            end_at: time::Instant::now() + timeout,
+            end_at: crate::time::now() + timeout,
             timeout,
         }
     }
 
-    pub async fn timeout(&self) {
-        let delay = self.end_at - time::Instant::now();
-        if delay <= time::Duration::from_millis(0) {
-            return;
-        }
-
-        sleep(delay).await;
+    pub fn deadline(&self) -> time::Instant {
+        self.end_at
     }
 }
 
@@ -70,26 +109,25 @@ impl ConnTimeout {
     }
 
     #[inline]
-    pub(crate) async fn timeout(&self) {
-        self._timeout.timeout().await
+    pub fn deadline(&self) -> time::Instant {
+        self._timeout.deadline()
     }
 
+    // 单次读写的截止时间从当前开始计算
     #[inline]
-    pub(crate) async fn read_timeout(&self) {
+    pub(crate) fn read_deadline(&self) -> time::Instant {
         self._read_timeout
             .as_ref()
             .map_or(Timeout::new(config::DEFAULT_CONN_TIMEOUT), Clone::clone)
-            .timeout()
-            .await
+            .deadline()
     }
 
     #[inline]
-    pub(crate) async fn write_timeout(&self) {
+    pub(crate) fn write_deadline(&self) -> time::Instant {
         self._write_timeout
             .as_ref()
             .map_or(Timeout::new(config::DEFAULT_CONN_TIMEOUT), Clone::clone)
-            .timeout()
-            .await
+            .deadline()
     }
 }
 
@@ -108,3 +146,46 @@ impl Clone for ConnTimeout {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, time};
+
+    use crate::{
+        result::Result,
+        sleep,
+        sync::{mutex::AsyncMutex, wait_group::WaitGroup},
+        timeout::{timeout, timeout_at},
+    };
+
+    #[rt_entry::test]
+    async fn test_timeout() -> Result<()> {
+        let dur = time::Duration::from_millis(50);
+        assert_eq!(timeout(dur, async { 1 }).await?, 1);
+
+        let start = time::Instant::now();
+        let res = timeout(dur, sleep(time::Duration::from_secs(10))).await;
+        assert!(res.is_err_and(|e| e.is_timeout()));
+        assert!(start.elapsed() >= dur);
+
+        // 等待锁超时
+        let mtx = Rc::new(AsyncMutex::new(0));
+        let guard = mtx.lock().await;
+        assert!(timeout(dur, mtx.lock()).await.is_err());
+        drop(guard);
+        *timeout(dur, mtx.lock()).await? += 1;
+
+        // 等待WaitGroup超时
+        let wg = Rc::new(WaitGroup::new());
+        let w = wg.clone();
+        spawn!(async move {
+            let _guard = w.add();
+            sleep(time::Duration::from_millis(100)).await;
+        });
+        sleep(time::Duration::from_millis(10)).await;
+        assert!(timeout(dur, wg.wait()).await.is_err());
+        timeout_at(time::Instant::now() + dur * 4, wg.wait()).await?;
+
+        Ok(())
+    }
+}
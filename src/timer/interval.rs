@@ -0,0 +1,140 @@
+use std::time::{self, Instant};
+
+use crate::{time::now, timer::Sleeper};
+
+/// 错过tick（处理耗时超过一个周期）之后的补偿方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MissedTickBehavior {
+    // 立即补齐错过的tick，之后恢复原有的节奏
+    #[default]
+    Burst,
+    // 从当前时间重新开始计算周期
+    Delay,
+    // 丢弃错过的tick，对齐到原有节奏的下一个tick
+    Skip,
+}
+
+/*
+ * 周期定时器，每个tick都以上一个tick的计划时间为基准，不会因处理耗时而产生漂移
+ * tick()返回的future在完成前被丢弃时不会影响下一次tick
+ */
+pub struct Interval {
+    period: time::Duration,
+    next: Instant,
+    missed_tick_behavior: MissedTickBehavior,
+}
+
+/// 第一个tick立即完成
+pub fn interval(period: time::Duration) -> Interval {
+    interval_at(now(), period)
+}
+
+/// 第一个tick在start时完成
+pub fn interval_at(start: Instant, period: time::Duration) -> Interval {
+    assert!(!period.is_zero(), "interval period must be non-zero");
+    Interval {
+        period,
+        next: start,
+        missed_tick_behavior: MissedTickBehavior::default(),
+    }
+}
+
+impl Interval {
+    // 等待下一个tick，返回该tick的计划时间
+    pub async fn tick(&mut self) -> Instant {
+        let deadline = self.next;
+        if now() < deadline {
+            Sleeper::until(deadline).await;
+        }
+
+        self.next = self.next_deadline(deadline, now());
+        deadline
+    }
+
+    // 下一个tick从当前时间起一个周期后完成
+    pub fn reset(&mut self) {
+        self.next = now() + self.period;
+    }
+
+    pub fn period(&self) -> time::Duration {
+        self.period
+    }
+
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+
+    fn next_deadline(&self, deadline: Instant, now: Instant) -> Instant {
+        let next = deadline + self.period;
+        // 未错过下一个tick时保持原有的节奏
+        if now < next {
+            return next;
+        }
+
+        match self.missed_tick_behavior {
+            MissedTickBehavior::Burst => next,
+            MissedTickBehavior::Delay => now + self.period,
+            MissedTickBehavior::Skip => {
+                let missed = (now - deadline).as_nanos() / self.period.as_nanos();
+                deadline + self.period * (missed as u32 + 1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{self, Instant};
+
+    use crate::{
+        sleep,
+        timer::interval::{MissedTickBehavior, interval, interval_at},
+    };
+
+    #[rt_entry::test]
+    async fn test_interval() {
+        let period = time::Duration::from_millis(20);
+        let mut ticker = interval(period);
+        let start = ticker.tick().await;
+        for i in 1..5 {
+            // 处理耗时不影响tick的计划时间
+            assert_eq!(ticker.tick().await, start + period * i);
+            sleep(time::Duration::from_millis(5)).await;
+        }
+        assert!(start.elapsed() >= period * 4);
+
+        let reset_at = Instant::now();
+        ticker.reset();
+        assert!(ticker.tick().await >= reset_at + period);
+    }
+
+    #[rt_entry::test]
+    async fn test_missed_tick() {
+        let period = time::Duration::from_millis(50);
+        for behavior in [
+            MissedTickBehavior::Burst,
+            MissedTickBehavior::Delay,
+            MissedTickBehavior::Skip,
+        ] {
+            let start = Instant::now();
+            let mut ticker = interval_at(start, period);
+            ticker.set_missed_tick_behavior(behavior);
+            ticker.tick().await;
+            // 错过两个多周期
+            sleep(time::Duration::from_millis(110)).await;
+            let late = ticker.tick().await;
+            assert_eq!(late, start + period);
+            let after = ticker.tick().await;
+
+            match behavior {
+                MissedTickBehavior::Burst => assert_eq!(after, start + period * 2),
+                MissedTickBehavior::Delay => assert!(after > start + period * 3),
+                MissedTickBehavior::Skip => assert_eq!(after, start + period * 3),
+            }
+        }
+    }
+}
@@ -6,13 +6,19 @@ use std::{
     time::{self, Instant},
 };
 
+pub mod interval;
+pub mod wheel;
+
 use crate::{
     collections::box_ptr_set::{BoxPtrSet, BoxPtrSetDropper, SetPtr},
-    runtime::{add_timer, can_finish},
+    runtime::{add_timer, dump::WaitOn, wait_on},
+    timer::wheel::TimerDropper,
 };
 
+// 基于最大堆的定时器队列，取消的定时器只有到达堆顶时才会被清理
+// 运行时已改用TimerWheel，保留用于对比测试
 #[derive(Default)]
-pub(crate) struct PriorityTimerQueue {
+pub struct PriorityTimerQueue {
     inner: BinaryHeap<Timer>,
     set: BoxPtrSet<Waker>,
 }
@@ -25,8 +31,7 @@ impl PriorityTimerQueue {
         self.set.build_dropper(set_ptr)
     }
 
-    pub fn get_wakers(&mut self) -> Vec<Waker> {
-        let now = Instant::now();
+    pub fn get_wakers(&mut self, now: Instant) -> Vec<Waker> {
         let mut wakers = Vec::new();
         while let Some(timer) = self.inner.peek()
             && timer.wake_at <= now
@@ -40,17 +45,22 @@ impl PriorityTimerQueue {
         wakers
     }
 
-    pub fn delay(&mut self) -> Option<time::Duration> {
+    pub fn take_all(&mut self) -> Vec<Waker> {
+        self.inner
+            .drain()
+            .filter_map(|timer| self.set.remove(&timer.set_ptr))
+            .collect()
+    }
+
+    pub fn delay(&mut self, now: Instant) -> Option<time::Duration> {
         while let Some(timer) = self.inner.peek()
             && !self.set.contains(&timer.set_ptr)
         {
             self.inner.pop();
         }
-        // 由于涉及到任务的删除操作，所以需要判断是否仍存在其它任务，避免永久的block
-        if can_finish() {
-            return Some(time::Duration::from_secs(0));
-        }
-        self.inner.peek().map(|t| t.wake_at - time::Instant::now())
+        self.inner
+            .peek()
+            .map(|t| t.wake_at.saturating_duration_since(now))
     }
 }
 
@@ -89,7 +99,7 @@ impl Ord for Timer {
 pub struct Sleeper {
     wake_at: time::Instant,
     once: Once,
-    _dropper: RefCell<Option<BoxPtrSetDropper<Waker>>>,
+    _dropper: RefCell<Option<TimerDropper>>,
 }
 
 impl Sleeper {
@@ -102,7 +112,7 @@ impl Sleeper {
     }
 
     pub fn delay(delay: time::Duration) -> Self {
-        Self::until(time::Instant::now() + delay)
+        Self::until(crate::time::now() + delay)
     }
 }
 
@@ -113,13 +123,14 @@ impl Future for Sleeper {
         self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
-        if time::Instant::now() < self.wake_at {
+        if crate::time::now() < self.wake_at {
             // 保证当前timer只会添加一次
             self.once.call_once(|| {
                 self._dropper
                     .borrow_mut()
                     .replace(add_timer(self.wake_at, cx.waker().clone()));
             });
+            wait_on(WaitOn::Timer(self.wake_at));
             std::task::Poll::Pending
         } else {
             std::task::Poll::Ready(())
@@ -131,12 +142,14 @@ impl Future for Sleeper {
 mod tests {
     use core::time;
 
-    use crate::sleep;
+    use crate::{sleep, time::now};
 
-    #[rt_entry::test]
+    #[rt_entry::test(start_paused = true)]
     async fn test_sleep() {
         log::info!("in test");
+        let start = now();
         sleep(time::Duration::from_secs(1)).await;
+        assert_eq!(now() - start, time::Duration::from_secs(1));
         log::info!("test done");
     }
 }
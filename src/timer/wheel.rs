@@ -0,0 +1,383 @@
+use std::{
+    task::Waker,
+    time::{self, Instant},
+};
+
+use crate::collections::ShareMutable;
+
+// 每层的槽位数量为2^SLOT_BITS，一个tick为1ms
+const SLOT_BITS: usize = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 6;
+// 时间轮可表示的最大tick数（约795天），更远的定时器按此上限处理
+const MAX_TICKS: u64 = (1 << (SLOT_BITS * LEVELS)) - 1;
+const NIL: usize = usize::MAX;
+
+struct Entry {
+    // 到期的tick
+    when: u64,
+    waker: Waker,
+    level: usize,
+    slot: usize,
+    // 槽位内的双向链表，保证O(1)删除
+    prev: usize,
+    next: usize,
+}
+
+struct Level {
+    heads: [usize; SLOTS],
+    // 非空槽位的位图
+    occupied: u64,
+}
+
+impl Level {
+    fn new() -> Self {
+        Self {
+            heads: [NIL; SLOTS],
+            occupied: 0,
+        }
+    }
+}
+
+// 每层每个槽位覆盖的tick数
+fn slot_range(level: usize) -> u64 {
+    1 << (SLOT_BITS * level)
+}
+
+// 每层覆盖的tick数
+fn level_range(level: usize) -> u64 {
+    1 << (SLOT_BITS * (level + 1))
+}
+
+// 根据当前tick与到期tick的最高不同位确定所在的层
+fn level_for(elapsed: u64, when: u64) -> usize {
+    let masked = ((elapsed ^ when) | (SLOTS as u64 - 1)).min(MAX_TICKS - 1);
+    (63 - masked.leading_zeros() as usize) / SLOT_BITS
+}
+
+struct WheelInner {
+    start: Instant,
+    // 已经处理到的tick
+    elapsed: u64,
+    levels: Vec<Level>,
+    // 定时器存储，下标与代数共同作为定时器的key，避免释放后的下标被复用时误删
+    entries: Vec<(u64, Option<Entry>)>,
+    free: Vec<usize>,
+}
+
+impl WheelInner {
+    fn new(start: Instant) -> Self {
+        Self {
+            start,
+            elapsed: 0,
+            levels: (0..LEVELS).map(|_| Level::new()).collect(),
+            entries: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    // 向上取整，保证定时器不会提前触发
+    fn tick_ceil(&self, at: Instant) -> u64 {
+        let dur = at.saturating_duration_since(self.start);
+        let ms = dur.as_millis() as u64;
+        if dur > time::Duration::from_millis(ms) {
+            ms + 1
+        } else {
+            ms
+        }
+    }
+
+    fn tick_floor(&self, at: Instant) -> u64 {
+        at.saturating_duration_since(self.start).as_millis() as u64
+    }
+
+    fn insert(&mut self, wake_at: Instant, waker: Waker) -> (usize, u64) {
+        // 已到期的定时器在下一个tick触发
+        let when = self
+            .tick_ceil(wake_at)
+            .clamp(self.elapsed + 1, self.elapsed + MAX_TICKS);
+        let entry = Entry {
+            when,
+            waker,
+            level: 0,
+            slot: 0,
+            prev: NIL,
+            next: NIL,
+        };
+
+        let key = match self.free.pop() {
+            Some(key) => {
+                self.entries[key].1.replace(entry);
+                key
+            }
+            None => {
+                self.entries.push((0, Some(entry)));
+                self.entries.len() - 1
+            }
+        };
+        self.link(key);
+        (key, self.entries[key].0)
+    }
+
+    fn remove(&mut self, key: usize, generation: u64) -> Option<Waker> {
+        if self.entries.get(key)?.0 != generation || self.entries[key].1.is_none() {
+            return None;
+        }
+
+        self.unlink(key);
+        Some(self.release(key))
+    }
+
+    fn entry(&mut self, key: usize) -> &mut Entry {
+        self.entries[key].1.as_mut().unwrap()
+    }
+
+    fn release(&mut self, key: usize) -> Waker {
+        let (generation, entry) = &mut self.entries[key];
+        *generation += 1;
+        self.free.push(key);
+        entry.take().unwrap().waker
+    }
+
+    fn link(&mut self, key: usize) {
+        let elapsed = self.elapsed;
+        let when = self.entry(key).when;
+        let level = level_for(elapsed, when);
+        let slot = ((when >> (level * SLOT_BITS)) as usize) % SLOTS;
+        let next = self.levels[level].heads[slot];
+
+        let entry = self.entry(key);
+        entry.level = level;
+        entry.slot = slot;
+        entry.prev = NIL;
+        entry.next = next;
+        if next != NIL {
+            self.entry(next).prev = key;
+        }
+        self.levels[level].heads[slot] = key;
+        self.levels[level].occupied |= 1 << slot;
+    }
+
+    fn unlink(&mut self, key: usize) {
+        let entry = self.entry(key);
+        let (level, slot, prev, next) = (entry.level, entry.slot, entry.prev, entry.next);
+        if prev == NIL {
+            self.levels[level].heads[slot] = next;
+        } else {
+            self.entry(prev).next = next;
+        }
+        if next != NIL {
+            self.entry(next).prev = prev;
+        }
+
+        if self.levels[level].heads[slot] == NIL {
+            self.levels[level].occupied &= !(1 << slot);
+        }
+    }
+
+    // 最早的非空槽位及其起始tick。低层槽位的起始tick总是早于高层
+    fn next_expiration(&self) -> Option<(usize, usize, u64)> {
+        for (level, lv) in self.levels.iter().enumerate() {
+            if lv.occupied == 0 {
+                continue;
+            }
+
+            let now_slot = (self.elapsed / slot_range(level)) as usize % SLOTS;
+            let zeros = lv.occupied.rotate_right(now_slot as u32).trailing_zeros() as usize;
+            let slot = (zeros + now_slot) % SLOTS;
+
+            let level_start = self.elapsed & !(level_range(level) - 1);
+            let mut deadline = level_start + slot as u64 * slot_range(level);
+            if deadline <= self.elapsed {
+                // 只有最高层会出现绕过一圈的情况
+                deadline += level_range(level);
+            }
+            return Some((level, slot, deadline));
+        }
+        None
+    }
+
+    // 取出槽位中的全部定时器，到期的触发，其余的下沉到更低的层
+    fn process_slot(&mut self, level: usize, slot: usize, wakers: &mut Vec<Waker>) {
+        let mut key = self.levels[level].heads[slot];
+        self.levels[level].heads[slot] = NIL;
+        self.levels[level].occupied &= !(1 << slot);
+
+        while key != NIL {
+            let next = self.entry(key).next;
+            if self.entry(key).when <= self.elapsed {
+                wakers.push(self.release(key));
+            } else {
+                self.link(key);
+            }
+            key = next;
+        }
+    }
+
+    fn poll(&mut self, now: Instant) -> Vec<Waker> {
+        let now_tick = self.tick_floor(now);
+        let mut wakers = Vec::new();
+        while let Some((level, slot, deadline)) = self.next_expiration()
+            && deadline <= now_tick
+        {
+            self.elapsed = deadline;
+            self.process_slot(level, slot, &mut wakers);
+        }
+        self.elapsed = self.elapsed.max(now_tick);
+
+        wakers
+    }
+
+    fn delay(&self, now: Instant) -> Option<time::Duration> {
+        self.next_expiration().map(|(_, _, deadline)| {
+            (self.start + time::Duration::from_millis(deadline)).saturating_duration_since(now)
+        })
+    }
+
+    fn drain(&mut self) -> Vec<Waker> {
+        for level in self.levels.iter_mut() {
+            *level = Level::new();
+        }
+        (0..self.entries.len())
+            .filter(|&key| self.entries[key].1.is_some())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|key| self.release(key))
+            .collect()
+    }
+}
+
+/*
+ * 分层时间轮，插入与取消都是O(1)
+ * 共6层，每层64个槽位，第0层每个槽位1ms。定时器按到期时间放入对应的层，随时间推进逐层下沉，最终在第0层触发
+ */
+#[derive(Clone)]
+pub struct TimerWheel {
+    inner: ShareMutable<WheelInner>,
+}
+
+impl Default for TimerWheel {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl TimerWheel {
+    // start为第0个tick的时间
+    pub fn new(start: Instant) -> Self {
+        Self {
+            inner: ShareMutable::new(WheelInner::new(start)),
+        }
+    }
+
+    pub fn add_timer(&mut self, wake_at: Instant, waker: Waker) -> TimerDropper {
+        let (key, generation) = self.inner.borrow_mut().insert(wake_at, waker);
+        TimerDropper {
+            inner: self.inner.clone(),
+            key,
+            generation,
+        }
+    }
+
+    // 取出now之前到期的定时器
+    pub fn get_wakers(&mut self, now: Instant) -> Vec<Waker> {
+        self.inner.borrow_mut().poll(now)
+    }
+
+    pub fn take_all(&mut self) -> Vec<Waker> {
+        self.inner.borrow_mut().drain()
+    }
+
+    pub fn delay(&mut self, now: Instant) -> Option<time::Duration> {
+        self.inner.borrow().delay(now)
+    }
+
+    pub fn len(&self) -> usize {
+        let inner = self.inner.borrow();
+        inner.entries.len() - inner.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// 释放时取消对应的定时器
+pub struct TimerDropper {
+    inner: ShareMutable<WheelInner>,
+    key: usize,
+    generation: u64,
+}
+
+impl Drop for TimerDropper {
+    fn drop(&mut self) {
+        // waker需要在借用结束后再释放，其释放过程可能再次操作时间轮
+        let waker = self.inner.borrow_mut().remove(self.key, self.generation);
+        drop(waker);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        task::{Wake, Waker},
+        time::{Duration, Instant},
+    };
+
+    use crate::timer::wheel::WheelInner;
+
+    struct CountWaker(AtomicUsize);
+
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_timer_wheel() {
+        let mut wheel = WheelInner::new(Instant::now());
+        let start = wheel.start;
+        let counter = Arc::new(CountWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+
+        let delays = [1, 63, 64, 65, 4095, 4097, 300_000, 3_600_000];
+        let mut keys = Vec::new();
+        for ms in delays {
+            keys.push(wheel.insert(start + Duration::from_millis(ms), waker.clone()));
+        }
+        // 取消一个，其余的都应在到期时（且不早于到期时）触发
+        let (key, generation) = keys[2];
+        assert!(wheel.remove(key, generation).is_some());
+        assert!(wheel.remove(key, generation).is_none());
+
+        let mut fired = Vec::new();
+        let mut now = start;
+        while let Some(delay) = wheel.delay(now) {
+            now += delay;
+            for waker in wheel.poll(now) {
+                waker.wake();
+                fired.push(now.duration_since(start).as_millis() as u64);
+            }
+        }
+
+        let expected: Vec<u64> = delays
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != 2)
+            .map(|(_, &ms)| ms)
+            .collect();
+        assert_eq!(fired, expected);
+        assert_eq!(counter.0.load(Ordering::Relaxed), expected.len());
+        assert_eq!(wheel.entries.len() - wheel.free.len(), 0);
+
+        // 已到期的定时器在下一个tick触发
+        wheel.insert(start, waker.clone());
+        assert_eq!(wheel.poll(now + Duration::from_millis(1)).len(), 1);
+        assert!(wheel.drain().is_empty());
+    }
+}
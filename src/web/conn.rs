@@ -9,10 +9,10 @@ use crate::{
         write::TAsyncWrite,
     },
     result::{ErrorType, Result},
-    select,
+    runtime::coop::consume_budget,
     sync::mutex::AsyncMutex,
     tcp::stream::Stream,
-    timeout::ConnTimeout,
+    timeout::{ConnTimeout, timeout_at},
     udp::Udp,
 };
 
@@ -77,19 +77,11 @@ impl<T: TAsyncRead + TAsyncWrite> _Conn<T> {
 impl<T: TAsyncRead + TAsyncWrite> TAsyncRead for _Conn<T> {
     fn ready_to_read(&mut self) -> crate::BoxedFuture<'_, ()> {
         Box::pin(async {
-            select! {
-                _ = self.timeout.timeout() => {
-                    return Err(ErrorType::Timeout.into());
-                },
-                _ = self.timeout.read_timeout() => {
-                    return Err(ErrorType::ReadTimeout.into());
-                },
-                result = self.inner.ready_to_read() => {
-                    err_log!(result, ".ready_to_read() failed")?;
-                }
+            let ready = timeout_at(self.timeout.read_deadline(), self.inner.ready_to_read());
+            match timeout_at(self.timeout.deadline(), ready).await? {
+                Ok(result) => err_log!(result, ".ready_to_read() failed"),
+                Err(_) => Err(ErrorType::ReadTimeout.into()),
             }
-
-            Ok(())
         })
     }
 
@@ -111,6 +103,7 @@ impl<T: TAsyncRead + TAsyncWrite> TAsyncBufRead for _Conn<T> {
             let mut buf = [0u8; READ_BUF_SIZE];
             self.ready_to_read().await?;
             loop {
+                consume_budget().await;
                 let size = self.read(&mut buf)?;
                 self.buf.extend_from_slice(&buf[..size]);
                 if let Some(at) = read_while(&self.buf) {
@@ -124,19 +117,11 @@ impl<T: TAsyncRead + TAsyncWrite> TAsyncBufRead for _Conn<T> {
 impl<T: TAsyncRead + TAsyncWrite> TAsyncWrite for _Conn<T> {
     fn ready_to_write(&mut self) -> crate::BoxedFuture<'_, ()> {
         Box::pin(async {
-            select! {
-                _ = self.timeout.timeout() =>  {
-                    return Err(ErrorType::Timeout.into());
-                },
-                _ = self.timeout.write_timeout() => {
-                    return Err(ErrorType::WriteTimeout.into());
-                },
-                result = self.inner.ready_to_write() => {
-                    err_log!(result, ".ready_to_write() failed")?;
-                }
+            let ready = timeout_at(self.timeout.write_deadline(), self.inner.ready_to_write());
+            match timeout_at(self.timeout.deadline(), ready).await? {
+                Ok(result) => err_log!(result, ".ready_to_write() failed"),
+                Err(_) => Err(ErrorType::WriteTimeout.into()),
             }
-
-            Ok(())
         })
     }
 
@@ -1,14 +1,13 @@
 use core::time;
 
 use crate::{
-    BoxedFutureWithError,
+    BoxedFutureWithError, MissedTickBehavior,
     dns::cache::open_dns_cache_refresh,
-    err_log,
+    err_log, interval,
     result::Result,
     runtime::spawn,
     select,
     signal::{StopWaker, set_max_wait_duration},
-    sleep,
     tcp::listener::Listener,
     timeout::ConnTimeout,
     web::conn::{SharedTcpConn, new_tcp_conn},
@@ -49,7 +48,8 @@ where
     }
 
     pub async fn run(&mut self) -> Result<()> {
-        let dur = time::Duration::from_secs(2);
+        let mut heartbeat = interval(time::Duration::from_secs(2));
+        heartbeat.set_missed_tick_behavior(MissedTickBehavior::Skip);
         loop {
             select! {
                 Err(e) = self.listener.ready() => {
@@ -58,7 +58,7 @@ where
                 } || {
                     log::debug!("server .accept() ready");
                 },
-                _ = sleep(dur) => {
+                _ = heartbeat.tick() => {
                     log::debug!("server heartbeat");
                 },
                 _ = self.stop_waker.wait() => {